@@ -11,8 +11,8 @@ use monad_quad::{
 };
 
 use spine_node::{
-    setup_runtime, AnimationEvent, AnimationOptions, AnimationStateWithData, LoadSpineFromBytes,
-    Spine, SpineAnimation, SpineState,
+    setup_runtime, AnimationEvent, AnimationOptions, AnimationStateWithData, LoadSpineFromFile,
+    SpineAnimation, SpineSkeletonPath, SpineState,
 };
 
 struct MainState {
@@ -77,21 +77,10 @@ fn animation_node() -> impl for<'c> Component<
 
 async fn load_spine() -> SpineState {
     let token = setup_runtime();
-    let base_path = "./examples/asset_exports/".to_string();
-    let atlas = macroquad::file::load_file(&(base_path.clone() + "skeleton.atlas"))
-        .await
-        .unwrap();
-    let skeleton = macroquad::file::load_file(&(base_path.clone() + "skeleton.json"))
-        .await
-        .unwrap();
-    let texture = macroquad::texture::load_texture(&(base_path.clone() + "skeleton.png"))
-        .await
-        .unwrap();
-    let spine = Spine::load_from_bytes(
-        LoadSpineFromBytes {
-            atlas: atlas.into(),
-            dir_path: base_path.into(),
-            skeleton_data: spine_node::SpineSkeletonBytes::Json(skeleton.into()),
+    SpineState::load_async(
+        LoadSpineFromFile {
+            atlas_path: "./examples/asset_exports/skeleton.atlas".to_string(),
+            skeleton_path: SpineSkeletonPath::Json("./examples/asset_exports/skeleton.json".into()),
             animation: Some(AnimationOptions {
                 looping: true,
                 animation_name: "wag_tail".to_string(),
@@ -100,10 +89,11 @@ async fn load_spine() -> SpineState {
             scale: 0.5,
             skin: Some("no_mask".to_string()),
             backface_culling: true,
-            preloaded_texture: Some(texture),
+            default_mix: 0.2,
+            mixes: vec![],
         },
-        token.clone(),
+        token,
     )
-    .unwrap();
-    SpineState::new(spine, token).unwrap()
+    .await
+    .unwrap()
 }
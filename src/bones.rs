@@ -0,0 +1,191 @@
+use macroquad::prelude::{vec2, Vec2};
+use rusty_spine::{Bone, Skeleton};
+
+use crate::{Spine, SpineNodeError, SpineState};
+
+/// The transform of a bone relative to its parent. Rotation and shear are in degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoneTransform {
+    pub position: Vec2,
+    pub rotation: f32,
+    pub scale: Vec2,
+    pub shear: Vec2,
+}
+
+/// The transform of a bone after applying all of its parents. Rotation is in degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoneWorldTransform {
+    pub position: Vec2,
+    pub rotation: f32,
+    pub scale: Vec2,
+}
+
+/// Replaces parts of the local transform of a bone after the animations are applied.
+///
+/// Fields that are `None` keep the animated value.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BoneOverride {
+    pub position: Option<Vec2>,
+    /// In degrees.
+    pub rotation: Option<f32>,
+    pub scale: Option<Vec2>,
+    /// In degrees.
+    pub shear: Option<Vec2>,
+}
+
+impl BoneOverride {
+    fn apply(&self, bone: &mut Bone) {
+        if let Some(position) = self.position {
+            bone.set_x(position.x);
+            bone.set_y(position.y);
+        }
+        if let Some(rotation) = self.rotation {
+            bone.set_rotation(rotation);
+        }
+        if let Some(scale) = self.scale {
+            bone.set_scale_x(scale.x);
+            bone.set_scale_y(scale.y);
+        }
+        if let Some(shear) = self.shear {
+            bone.set_shear_x(shear.x);
+            bone.set_shear_y(shear.y);
+        }
+    }
+    /// Puts the overridden parts of the local transform back to the setup pose.
+    fn restore(&self, bone: &mut Bone) {
+        let data = bone.data();
+        let (x, y, rotation) = (data.x(), data.y(), data.rotation());
+        let (scale_x, scale_y) = (data.scale_x(), data.scale_y());
+        let (shear_x, shear_y) = (data.shear_x(), data.shear_y());
+        if self.position.is_some() {
+            bone.set_x(x);
+            bone.set_y(y);
+        }
+        if self.rotation.is_some() {
+            bone.set_rotation(rotation);
+        }
+        if self.scale.is_some() {
+            bone.set_scale_x(scale_x);
+            bone.set_scale_y(scale_y);
+        }
+        if self.shear.is_some() {
+            bone.set_shear_x(shear_x);
+            bone.set_shear_y(shear_y);
+        }
+    }
+}
+
+impl Spine {
+    fn restore_bone(&self, name: &str, bone_override: &BoneOverride) {
+        let mut controller = self.controller.borrow_mut();
+        if let Some(mut bone) = controller.skeleton.find_bone_mut(name) {
+            bone_override.restore(&mut bone);
+        }
+        controller.skeleton.update_world_transform();
+    }
+    pub(crate) fn apply_bone_overrides(&self, skeleton: &mut Skeleton) {
+        for (name, bone_override) in &self.bone_overrides {
+            if let Some(mut bone) = skeleton.find_bone_mut(name) {
+                bone_override.apply(&mut bone);
+            }
+        }
+    }
+}
+
+impl SpineState {
+    fn with_bone<T>(&self, name: &str, cb: impl FnOnce(&Bone) -> T) -> Result<T, SpineNodeError> {
+        let spine = self.spine.borrow();
+        let controller = spine.controller.borrow();
+        let bone =
+            controller
+                .skeleton
+                .find_bone(name)
+                .ok_or_else(|| SpineNodeError::UnknownBone {
+                    name: name.to_string(),
+                })?;
+        Ok(cb(&bone))
+    }
+    pub fn bone_local_transform(&self, name: &str) -> Result<BoneTransform, SpineNodeError> {
+        self.with_bone(name, |bone| BoneTransform {
+            position: vec2(bone.x(), bone.y()),
+            rotation: bone.rotation(),
+            scale: vec2(bone.scale_x(), bone.scale_y()),
+            shear: vec2(bone.shear_x(), bone.shear_y()),
+        })
+    }
+    /// The transform of a bone in skeleton space.
+    pub fn bone_skeleton_transform(
+        &self,
+        name: &str,
+    ) -> Result<BoneWorldTransform, SpineNodeError> {
+        self.with_bone(name, |bone| BoneWorldTransform {
+            position: vec2(bone.world_x(), bone.world_y()),
+            rotation: bone.world_rotation_x(),
+            scale: vec2(bone.world_scale_x(), bone.world_scale_y()),
+        })
+    }
+    /// The transform of a bone in world space, so after applying the position, rotation and scale of this [SpineState].
+    pub fn bone_world_transform(&self, name: &str) -> Result<BoneWorldTransform, SpineNodeError> {
+        let world = self.world();
+        self.with_bone(name, |bone| {
+            let position = world
+                .transform_point3(vec2(bone.world_x(), bone.world_y()).extend(0.))
+                .truncate();
+            let x_axis = world
+                .transform_vector3(vec2(bone.a(), bone.c()).extend(0.))
+                .truncate();
+            let y_axis = world
+                .transform_vector3(vec2(bone.b(), bone.d()).extend(0.))
+                .truncate();
+            let mirrored = x_axis.perp_dot(y_axis) < 0.;
+            BoneWorldTransform {
+                position,
+                rotation: x_axis.y.atan2(x_axis.x).to_degrees(),
+                scale: vec2(
+                    x_axis.length(),
+                    if mirrored {
+                        -y_axis.length()
+                    } else {
+                        y_axis.length()
+                    },
+                ),
+            }
+        })
+    }
+    /// The position of a bone in screen space. See [SpineState::world_to_screen].
+    pub fn bone_screen_position(&self, name: &str) -> Result<Vec2, SpineNodeError> {
+        let position = self.bone_world_transform(name)?.position;
+        Ok(self.world_to_screen(position))
+    }
+    /// Overrides parts of the local transform of a bone every frame,
+    /// after the animations are applied but before the world transforms are calculated.
+    ///
+    /// The override stays active until [SpineState::clear_bone_override] is called.
+    pub fn set_bone_override(
+        &mut self,
+        name: &str,
+        bone_override: BoneOverride,
+    ) -> Result<(), SpineNodeError> {
+        self.with_bone(name, |_| ())?;
+        self.spine
+            .borrow_mut()
+            .bone_overrides
+            .insert(name.to_string(), bone_override);
+        Ok(())
+    }
+    /// Stops overriding a bone, putting the overridden parts back to the setup pose.
+    ///
+    /// Animations that key the bone take over again from the next frame.
+    pub fn clear_bone_override(&mut self, name: &str) {
+        let mut spine = self.spine.borrow_mut();
+        if let Some(bone_override) = spine.bone_overrides.remove(name) {
+            spine.restore_bone(name, &bone_override);
+        }
+    }
+    pub fn clear_bone_overrides(&mut self) {
+        let mut spine = self.spine.borrow_mut();
+        for (name, bone_override) in std::mem::take(&mut spine.bone_overrides) {
+            spine.restore_bone(&name, &bone_override);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use macroquad::{
+    math::Rect,
+    prelude::{vec2, Mat4, Vec2},
+};
+use rusty_spine::{
+    c::{
+        spAttachment, spSkeletonBounds, spSkeletonBounds_create, spSkeletonBounds_dispose,
+        spSkeletonBounds_getPolygon, spSkeletonBounds_update,
+    },
+    controller::SkeletonCombinedRenderable,
+    Skeleton,
+};
+
+use crate::{render_buffers::combined_renderables, SpineState};
+
+/// What to use to calculate the bounds of a skeleton.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoundsSource {
+    /// Everything that gets drawn.
+    Renderables,
+    /// The bounding box attachments that are currently visible.
+    BoundingBoxes,
+}
+
+/// Owns a `spSkeletonBounds`, which holds the polygons of all visible bounding box attachments.
+pub(crate) struct SkeletonBounds(*mut spSkeletonBounds);
+
+impl SkeletonBounds {
+    pub(crate) fn new(skeleton: &Skeleton) -> Self {
+        unsafe {
+            let bounds = spSkeletonBounds_create();
+            spSkeletonBounds_update(bounds, skeleton.c_ptr(), 1);
+            Self(bounds)
+        }
+    }
+    /// The polygon of a bounding box attachment, if it is currently visible.
+    pub(crate) fn polygon(&self, attachment: *mut spAttachment) -> Option<Vec<Vec2>> {
+        if attachment.is_null() {
+            return None;
+        }
+        unsafe {
+            let polygon = spSkeletonBounds_getPolygon(self.0, attachment.cast());
+            if polygon.is_null() {
+                return None;
+            }
+            let polygon = &*polygon;
+            Some(
+                std::slice::from_raw_parts(polygon.vertices, polygon.count as usize)
+                    .chunks_exact(2)
+                    .map(|v| vec2(v[0], v[1]))
+                    .collect(),
+            )
+        }
+    }
+    pub(crate) fn aabb(&self) -> Option<Rect> {
+        let bounds = unsafe { &*self.0 };
+        if bounds.count == 0 {
+            return None;
+        }
+        Some(Rect::new(
+            bounds.minX,
+            bounds.minY,
+            bounds.maxX - bounds.minX,
+            bounds.maxY - bounds.minY,
+        ))
+    }
+}
+
+impl Drop for SkeletonBounds {
+    fn drop(&mut self) {
+        unsafe { spSkeletonBounds_dispose(self.0) }
+    }
+}
+
+fn aabb(points: impl IntoIterator<Item = Vec2>) -> Option<Rect> {
+    let mut points = points.into_iter();
+    let first = points.next()?;
+    let (min, max) = points.fold((first, first), |(min, max), point| {
+        (min.min(point), max.max(point))
+    });
+    Some(Rect::new(min.x, min.y, max.x - min.x, max.y - min.y))
+}
+
+pub(crate) fn corners(rect: Rect) -> [Vec2; 4] {
+    [
+        vec2(rect.left(), rect.top()),
+        vec2(rect.right(), rect.top()),
+        vec2(rect.left(), rect.bottom()),
+        vec2(rect.right(), rect.bottom()),
+    ]
+}
+
+pub(crate) fn renderables_aabb(renderables: &[SkeletonCombinedRenderable]) -> Option<Rect> {
+    aabb(
+        renderables
+            .iter()
+            .flat_map(|renderable| renderable.vertices.iter())
+            .map(|&[x, y]| vec2(x, y)),
+    )
+}
+
+/// Whether any part of the given skeleton space rect ends up inside of the clip space of `world_view`.
+pub(crate) fn is_visible(rect: Rect, world_view: Mat4) -> bool {
+    let clip = corners(rect).map(|corner| world_view.project_point3(corner.extend(0.)));
+    !(clip.iter().all(|v| v.x < -1.)
+        || clip.iter().all(|v| v.x > 1.)
+        || clip.iter().all(|v| v.y < -1.)
+        || clip.iter().all(|v| v.y > 1.))
+}
+
+impl SpineState {
+    /// The axis aligned bounds of the skeleton in skeleton space, with y pointing up.
+    ///
+    /// Returns `None` if there is nothing to calculate the bounds of.
+    pub fn skeleton_bounds(&self, source: BoundsSource) -> Option<Rect> {
+        let spine = self.spine.borrow();
+        let mut controller = spine.controller.borrow_mut();
+        match source {
+            BoundsSource::Renderables => renderables_aabb(&combined_renderables(&mut controller)),
+            BoundsSource::BoundingBoxes => SkeletonBounds::new(&controller.skeleton).aabb(),
+        }
+    }
+    /// The axis aligned bounds of the skeleton in world space.
+    pub fn world_bounds(&self, source: BoundsSource) -> Option<Rect> {
+        let rect = self.skeleton_bounds(source)?;
+        aabb(corners(rect).map(|corner| self.skeleton_to_world(corner)))
+    }
+    /// The axis aligned bounds of the skeleton in screen space. See [SpineState::world_to_screen].
+    pub fn screen_bounds(&self, source: BoundsSource) -> Option<Rect> {
+        let rect = self.skeleton_bounds(source)?;
+        aabb(corners(rect).map(|corner| self.skeleton_to_screen(corner)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad::{
+        math::Rect,
+        prelude::{vec3, Mat4},
+    };
+
+    use super::is_visible;
+
+    #[test]
+    fn rects_in_clip_space() {
+        assert!(is_visible(Rect::new(-0.5, -0.5, 1., 1.), Mat4::IDENTITY));
+        // Partly visible
+        assert!(is_visible(Rect::new(0.5, 0.5, 1., 1.), Mat4::IDENTITY));
+        // Bigger than the whole clip space
+        assert!(is_visible(Rect::new(-5., -5., 10., 10.), Mat4::IDENTITY));
+        assert!(!is_visible(Rect::new(1.5, -0.5, 1., 1.), Mat4::IDENTITY));
+        assert!(!is_visible(Rect::new(-0.5, -2.5, 1., 1.), Mat4::IDENTITY));
+    }
+
+    #[test]
+    fn rects_moved_by_the_world_view() {
+        let rect = Rect::new(2., 0., 0.5, 0.5);
+        assert!(!is_visible(rect, Mat4::IDENTITY));
+        assert!(is_visible(rect, Mat4::from_translation(vec3(-2., 0., 0.))));
+        assert!(is_visible(rect, Mat4::from_scale(vec3(0.25, 0.25, 1.))));
+        // Rotated by 90 degrees it ends up above the clip space instead
+        let rotation = Mat4::from_rotation_z(std::f32::consts::FRAC_PI_2);
+        assert!(!is_visible(rect, rotation));
+        assert!(is_visible(
+            rect,
+            Mat4::from_translation(vec3(0., -2., 0.)) * rotation
+        ));
+    }
+}
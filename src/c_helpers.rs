@@ -0,0 +1,29 @@
+//! Helpers to read parts of the spine runtime that [rusty_spine] has no safe accessors for yet.
+
+use std::ffi::{c_char, c_int, CStr};
+
+/// Copies a (possibly null) c string into a [String].
+///
+/// # Safety
+///
+/// `ptr` has to be null or point to a valid, nul terminated string.
+pub(crate) unsafe fn c_string(ptr: *const c_char) -> String {
+    if ptr.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(ptr).to_string_lossy().into_owned()
+    }
+}
+
+/// Turns an array of pointers as used by the spine runtime into a slice.
+///
+/// # Safety
+///
+/// `ptr` has to point to at least `count` valid pointers, that stay valid for `'a`.
+pub(crate) unsafe fn c_array<'a, T>(ptr: *mut *mut T, count: c_int) -> &'a [*mut T] {
+    if ptr.is_null() || count <= 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(ptr, count as usize)
+    }
+}
@@ -0,0 +1,98 @@
+use macroquad::{
+    camera::Camera,
+    miniquad::RenderPass,
+    prelude::{vec2, Mat4, Vec2},
+    window::{get_internal_gl, screen_height, screen_width},
+};
+
+use crate::{coordinates::ScreenTransform, SpineState};
+
+/// How the world space of a [SpineState] ends up on the screen.
+///
+/// Skeletons are never mirrored by the camera. If a camera has y pointing down,
+/// the skeleton gets flipped so it still stands upright.
+#[derive(Debug, Clone, Default)]
+pub enum SpineCamera {
+    /// (0,0) is the center of the viewport, y points up and one unit is one pixel.
+    #[default]
+    Centered,
+    /// (0,0) is the top left of the viewport, y points down and one unit is one pixel.
+    ///
+    /// This matches macroquad's own drawing functions when no camera is set.
+    ScreenSpace,
+    /// Uses whatever camera is set in macroquad through [macroquad::camera::set_camera]
+    /// at the moment the skeleton is processed or rendered, including its render target.
+    ///
+    /// This allows using a different camera for every render call.
+    Active,
+    /// A fixed view matrix, optionally rendering into a render pass instead of the screen.
+    Custom {
+        view: Mat4,
+        render_pass: Option<RenderPass>,
+    },
+}
+
+/// Takes a snapshot of the camera. Set it again whenever the camera moves, or use [SpineCamera::Active].
+impl<C: Camera> From<&C> for SpineCamera {
+    fn from(camera: &C) -> Self {
+        Self::Custom {
+            view: camera.matrix(),
+            render_pass: camera.render_pass(),
+        }
+    }
+}
+
+impl SpineCamera {
+    /// The render pass to draw into, or `None` to draw to the screen.
+    fn render_pass(&self) -> Option<RenderPass> {
+        match self {
+            Self::Centered | Self::ScreenSpace => None,
+            Self::Active => unsafe { get_internal_gl() }
+                .quad_gl
+                .get_active_render_pass(),
+            Self::Custom { render_pass, .. } => *render_pass,
+        }
+    }
+    /// The size of the texture the camera renders into, if it doesn't render to the screen.
+    fn render_target_size(&self) -> Option<Vec2> {
+        let render_pass = self.render_pass()?;
+        let ctx = unsafe { get_internal_gl() }.quad_context;
+        let (width, height) = ctx.texture_size(ctx.render_pass_texture(render_pass));
+        Some(vec2(width as f32, height as f32))
+    }
+}
+
+impl ScreenTransform {
+    /// `viewport_size` is the size of the screen, which is replaced by the size of the render target if there is one.
+    pub(crate) fn from_camera(camera: &SpineCamera, viewport_size: Vec2) -> Self {
+        let viewport_size = camera.render_target_size().unwrap_or(viewport_size);
+        match camera {
+            SpineCamera::Centered => Self::centered(viewport_size),
+            SpineCamera::ScreenSpace => Self::screen_space(viewport_size),
+            SpineCamera::Active => Self {
+                view: unsafe { get_internal_gl() }.quad_gl.get_projection_matrix(),
+                viewport_size,
+            },
+            SpineCamera::Custom { view, .. } => Self {
+                view: *view,
+                viewport_size,
+            },
+        }
+    }
+}
+
+impl SpineState {
+    pub fn camera(&self) -> &SpineCamera {
+        &self.camera
+    }
+    pub fn set_camera(&mut self, camera: SpineCamera) {
+        let screen_size = vec2(screen_width(), screen_height());
+        self.screen
+            .set(ScreenTransform::from_camera(&camera, screen_size));
+        self.camera = camera;
+    }
+    /// The render pass to draw into, or `None` to draw to the screen.
+    pub(crate) fn render_pass(&self) -> Option<RenderPass> {
+        self.camera.render_pass()
+    }
+}
@@ -0,0 +1,354 @@
+use rusty_spine::{
+    c::{spPathConstraint, spTransformConstraint},
+    Skeleton,
+};
+
+use crate::{
+    c_helpers::{c_array, c_string},
+    Spine, SpineNodeError, SpineState,
+};
+
+/// How much a transform constraint affects each part of the transform of its bones, from 0 to 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransformConstraintMix {
+    pub rotate: f32,
+    pub x: f32,
+    pub y: f32,
+    pub scale_x: f32,
+    pub scale_y: f32,
+    pub shear_y: f32,
+}
+
+impl TransformConstraintMix {
+    fn read(constraint: &spTransformConstraint) -> Self {
+        Self {
+            rotate: constraint.mixRotate,
+            x: constraint.mixX,
+            y: constraint.mixY,
+            scale_x: constraint.mixScaleX,
+            scale_y: constraint.mixScaleY,
+            shear_y: constraint.mixShearY,
+        }
+    }
+    fn write(&self, constraint: &mut spTransformConstraint) {
+        constraint.mixRotate = self.rotate;
+        constraint.mixX = self.x;
+        constraint.mixY = self.y;
+        constraint.mixScaleX = self.scale_x;
+        constraint.mixScaleY = self.scale_y;
+        constraint.mixShearY = self.shear_y;
+    }
+}
+
+/// The position and spacing along the path, and how much a path constraint affects its bones.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathConstraintMix {
+    pub position: f32,
+    pub spacing: f32,
+    pub rotate: f32,
+    pub x: f32,
+    pub y: f32,
+}
+
+impl PathConstraintMix {
+    fn read(constraint: &spPathConstraint) -> Self {
+        Self {
+            position: constraint.position,
+            spacing: constraint.spacing,
+            rotate: constraint.mixRotate,
+            x: constraint.mixX,
+            y: constraint.mixY,
+        }
+    }
+    fn write(&self, constraint: &mut spPathConstraint) {
+        constraint.position = self.position;
+        constraint.spacing = self.spacing;
+        constraint.mixRotate = self.rotate;
+        constraint.mixX = self.x;
+        constraint.mixY = self.y;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TransformConstraintInfo {
+    pub name: String,
+    pub bones: Vec<String>,
+    pub target: String,
+    pub mix: TransformConstraintMix,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct PathConstraintInfo {
+    pub name: String,
+    pub bones: Vec<String>,
+    /// The slot that holds the path.
+    pub target: String,
+    pub mix: PathConstraintMix,
+    pub enabled: bool,
+}
+
+/// A constraint that was disabled through [SpineState::set_constraint_enabled],
+/// together with the mix it gets back once it is enabled again.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum DisabledConstraint {
+    Transform(TransformConstraintMix),
+    Path(PathConstraintMix),
+}
+
+/// # Safety
+///
+/// The returned pointer is only valid for as long as the skeleton is.
+unsafe fn find_transform_constraint(
+    skeleton: &Skeleton,
+    name: &str,
+) -> Option<*mut spTransformConstraint> {
+    let c_skeleton = &*skeleton.c_ptr();
+    c_array(
+        c_skeleton.transformConstraints,
+        c_skeleton.transformConstraintsCount,
+    )
+    .iter()
+    .copied()
+    .find(|&constraint| c_string((*(*constraint).data).name) == name)
+}
+
+/// # Safety
+///
+/// The returned pointer is only valid for as long as the skeleton is.
+unsafe fn find_path_constraint(skeleton: &Skeleton, name: &str) -> Option<*mut spPathConstraint> {
+    let c_skeleton = &*skeleton.c_ptr();
+    c_array(c_skeleton.pathConstraints, c_skeleton.pathConstraintsCount)
+        .iter()
+        .copied()
+        .find(|&constraint| c_string((*(*constraint).data).name) == name)
+}
+
+impl Spine {
+    /// Sets the mixes of all disabled constraints to 0, as animations may have changed them.
+    pub(crate) fn apply_disabled_constraints(&self, skeleton: &mut Skeleton) {
+        for (name, disabled) in &self.disabled_constraints {
+            unsafe {
+                match disabled {
+                    DisabledConstraint::Transform(_) => {
+                        if let Some(constraint) = find_transform_constraint(skeleton, name) {
+                            TransformConstraintMix {
+                                rotate: 0.,
+                                x: 0.,
+                                y: 0.,
+                                scale_x: 0.,
+                                scale_y: 0.,
+                                shear_y: 0.,
+                            }
+                            .write(&mut *constraint);
+                        }
+                    }
+                    DisabledConstraint::Path(_) => {
+                        if let Some(constraint) = find_path_constraint(skeleton, name) {
+                            let constraint = &mut *constraint;
+                            constraint.mixRotate = 0.;
+                            constraint.mixX = 0.;
+                            constraint.mixY = 0.;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl SpineState {
+    pub fn transform_constraints(&self) -> Vec<TransformConstraintInfo> {
+        let spine = self.spine.borrow();
+        let controller = spine.controller.borrow();
+        unsafe {
+            let c_skeleton = &*controller.skeleton.c_ptr();
+            c_array(
+                c_skeleton.transformConstraints,
+                c_skeleton.transformConstraintsCount,
+            )
+            .iter()
+            .map(|&constraint| {
+                let constraint = &*constraint;
+                let name = c_string((*constraint.data).name);
+                let disabled = spine.disabled_constraints.get(&name);
+                TransformConstraintInfo {
+                    bones: c_array(constraint.bones, constraint.bonesCount)
+                        .iter()
+                        .map(|&bone| c_string((*(*bone).data).name))
+                        .collect(),
+                    target: c_string((*(*constraint.target).data).name),
+                    mix: match disabled {
+                        Some(DisabledConstraint::Transform(mix)) => *mix,
+                        _ => TransformConstraintMix::read(constraint),
+                    },
+                    enabled: disabled.is_none(),
+                    name,
+                }
+            })
+            .collect()
+        }
+    }
+    pub fn path_constraints(&self) -> Vec<PathConstraintInfo> {
+        let spine = self.spine.borrow();
+        let controller = spine.controller.borrow();
+        unsafe {
+            let c_skeleton = &*controller.skeleton.c_ptr();
+            c_array(c_skeleton.pathConstraints, c_skeleton.pathConstraintsCount)
+                .iter()
+                .map(|&constraint| {
+                    let constraint = &*constraint;
+                    let name = c_string((*constraint.data).name);
+                    let disabled = spine.disabled_constraints.get(&name);
+                    PathConstraintInfo {
+                        bones: c_array(constraint.bones, constraint.bonesCount)
+                            .iter()
+                            .map(|&bone| c_string((*(*bone).data).name))
+                            .collect(),
+                        target: c_string((*(*constraint.target).data).name),
+                        mix: match disabled {
+                            Some(DisabledConstraint::Path(mix)) => *mix,
+                            _ => PathConstraintMix::read(constraint),
+                        },
+                        enabled: disabled.is_none(),
+                        name,
+                    }
+                })
+                .collect()
+        }
+    }
+    /// The mix of a transform constraint. For a disabled constraint this is the mix it gets once enabled.
+    pub fn transform_constraint_mix(
+        &self,
+        name: &str,
+    ) -> Result<TransformConstraintMix, SpineNodeError> {
+        let spine = self.spine.borrow();
+        if let Some(DisabledConstraint::Transform(mix)) = spine.disabled_constraints.get(name) {
+            return Ok(*mix);
+        }
+        let controller = spine.controller.borrow();
+        let constraint = unsafe { find_transform_constraint(&controller.skeleton, name) }
+            .ok_or_else(|| SpineNodeError::UnknownConstraint {
+                name: name.to_string(),
+            })?;
+        Ok(TransformConstraintMix::read(unsafe { &*constraint }))
+    }
+    /// Sets the mix of a transform constraint. For a disabled constraint this is the mix it gets once enabled.
+    ///
+    /// Animations that key this constraint will overwrite this.
+    pub fn set_transform_constraint_mix(
+        &mut self,
+        name: &str,
+        mix: TransformConstraintMix,
+    ) -> Result<(), SpineNodeError> {
+        let mut spine = self.spine.borrow_mut();
+        let spine = &mut *spine;
+        let controller = spine.controller.borrow();
+        let constraint = unsafe { find_transform_constraint(&controller.skeleton, name) }
+            .ok_or_else(|| SpineNodeError::UnknownConstraint {
+                name: name.to_string(),
+            })?;
+        match spine.disabled_constraints.get_mut(name) {
+            Some(DisabledConstraint::Transform(disabled_mix)) => *disabled_mix = mix,
+            _ => mix.write(unsafe { &mut *constraint }),
+        }
+        Ok(())
+    }
+    /// The mix of a path constraint. For a disabled constraint this is the mix it gets once enabled.
+    pub fn path_constraint_mix(&self, name: &str) -> Result<PathConstraintMix, SpineNodeError> {
+        let spine = self.spine.borrow();
+        if let Some(DisabledConstraint::Path(mix)) = spine.disabled_constraints.get(name) {
+            return Ok(*mix);
+        }
+        let controller = spine.controller.borrow();
+        let constraint =
+            unsafe { find_path_constraint(&controller.skeleton, name) }.ok_or_else(|| {
+                SpineNodeError::UnknownConstraint {
+                    name: name.to_string(),
+                }
+            })?;
+        Ok(PathConstraintMix::read(unsafe { &*constraint }))
+    }
+    /// Sets the mix of a path constraint. For a disabled constraint this is the mix it gets once enabled.
+    ///
+    /// Animations that key this constraint will overwrite this.
+    pub fn set_path_constraint_mix(
+        &mut self,
+        name: &str,
+        mix: PathConstraintMix,
+    ) -> Result<(), SpineNodeError> {
+        let mut spine = self.spine.borrow_mut();
+        let spine = &mut *spine;
+        let controller = spine.controller.borrow();
+        let constraint =
+            unsafe { find_path_constraint(&controller.skeleton, name) }.ok_or_else(|| {
+                SpineNodeError::UnknownConstraint {
+                    name: name.to_string(),
+                }
+            })?;
+        match spine.disabled_constraints.get_mut(name) {
+            Some(DisabledConstraint::Path(disabled_mix)) => {
+                // Position and spacing are not mixes, so they are applied right away.
+                *disabled_mix = mix;
+                unsafe {
+                    (*constraint).position = mix.position;
+                    (*constraint).spacing = mix.spacing;
+                }
+            }
+            _ => mix.write(unsafe { &mut *constraint }),
+        }
+        Ok(())
+    }
+    /// Enables or disables a transform or path constraint.
+    ///
+    /// A disabled constraint has its mixes kept at 0, even when an animation keys them.
+    /// Enabling it again restores the mix it had when it got disabled.
+    pub fn set_constraint_enabled(
+        &mut self,
+        name: &str,
+        enabled: bool,
+    ) -> Result<(), SpineNodeError> {
+        let mut spine = self.spine.borrow_mut();
+        let spine = &mut *spine;
+        let controller = spine.controller.borrow();
+        let skeleton = &controller.skeleton;
+        if let Some(constraint) = unsafe { find_transform_constraint(skeleton, name) } {
+            let constraint = unsafe { &mut *constraint };
+            if enabled {
+                if let Some(DisabledConstraint::Transform(mix)) =
+                    spine.disabled_constraints.remove(name)
+                {
+                    mix.write(constraint);
+                }
+            } else if !spine.disabled_constraints.contains_key(name) {
+                spine.disabled_constraints.insert(
+                    name.to_string(),
+                    DisabledConstraint::Transform(TransformConstraintMix::read(constraint)),
+                );
+            }
+        } else if let Some(constraint) = unsafe { find_path_constraint(skeleton, name) } {
+            let constraint = unsafe { &mut *constraint };
+            if enabled {
+                if let Some(DisabledConstraint::Path(mix)) = spine.disabled_constraints.remove(name)
+                {
+                    mix.write(constraint);
+                }
+            } else if !spine.disabled_constraints.contains_key(name) {
+                spine.disabled_constraints.insert(
+                    name.to_string(),
+                    DisabledConstraint::Path(PathConstraintMix::read(constraint)),
+                );
+            }
+        } else {
+            return Err(SpineNodeError::UnknownConstraint {
+                name: name.to_string(),
+            });
+        }
+        drop(controller);
+        spine.apply_disabled_constraints(&mut spine.controller.borrow_mut().skeleton);
+        Ok(())
+    }
+    pub fn is_constraint_enabled(&self, name: &str) -> bool {
+        !self.spine.borrow().disabled_constraints.contains_key(name)
+    }
+}
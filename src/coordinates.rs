@@ -0,0 +1,96 @@
+//! Conversions between the coordinate spaces a [SpineState] is drawn in.
+//!
+//! - skeleton space: the coordinates used inside of the skeleton, as set up in Spine.
+//! - world space: skeleton space after applying the position, rotation and scale of the [SpineState].
+//! - screen space: pixels, with (0,0) at the top left corner and y growing downwards.
+//!   When the camera renders into a texture, these are pixels of that texture.
+
+use macroquad::prelude::{vec2, vec3, Mat4, Vec2};
+
+use crate::SpineState;
+
+/// The view and viewport size of the last time the skeleton was processed or rendered.
+#[derive(Clone, Copy)]
+pub(crate) struct ScreenTransform {
+    pub(crate) view: Mat4,
+    pub(crate) viewport_size: Vec2,
+}
+
+impl ScreenTransform {
+    pub(crate) fn centered(viewport_size: Vec2) -> Self {
+        Self {
+            view: Mat4::orthographic_rh_gl(
+                viewport_size.x * -0.5,
+                viewport_size.x * 0.5,
+                viewport_size.y * -0.5,
+                viewport_size.y * 0.5,
+                0.,
+                1.,
+            ),
+            viewport_size,
+        }
+    }
+    pub(crate) fn screen_space(viewport_size: Vec2) -> Self {
+        Self {
+            view: Mat4::orthographic_rh_gl(0., viewport_size.x, viewport_size.y, 0., 0., 1.),
+            viewport_size,
+        }
+    }
+    /// Flips the skeleton vertically if the view would otherwise mirror it.
+    pub(crate) fn unmirror(&self) -> Mat4 {
+        let view = self.view;
+        if view.x_axis.x * view.y_axis.y - view.x_axis.y * view.y_axis.x < 0. {
+            Mat4::from_scale(vec3(1., -1., 1.))
+        } else {
+            Mat4::IDENTITY
+        }
+    }
+}
+
+impl SpineState {
+    /// The transform from skeleton space to world space.
+    pub(crate) fn world(&self) -> Mat4 {
+        self.spine.borrow().world() * self.screen.get().unmirror()
+    }
+    pub fn skeleton_to_world(&self, point: Vec2) -> Vec2 {
+        self.world().transform_point3(point.extend(0.)).truncate()
+    }
+    pub fn world_to_skeleton(&self, point: Vec2) -> Vec2 {
+        self.world()
+            .inverse()
+            .transform_point3(point.extend(0.))
+            .truncate()
+    }
+    /// Converts a point from world space to screen space,
+    /// using the view of the last time the skeleton was processed or rendered.
+    pub fn world_to_screen(&self, point: Vec2) -> Vec2 {
+        let ScreenTransform {
+            view,
+            viewport_size,
+        } = self.screen.get();
+        let clip = view.project_point3(point.extend(0.));
+        vec2(
+            (clip.x + 1.) * 0.5 * viewport_size.x,
+            (1. - clip.y) * 0.5 * viewport_size.y,
+        )
+    }
+    /// Converts a point from screen space to world space,
+    /// using the view of the last time the skeleton was processed or rendered.
+    pub fn screen_to_world(&self, point: Vec2) -> Vec2 {
+        let ScreenTransform {
+            view,
+            viewport_size,
+        } = self.screen.get();
+        let clip = vec2(
+            point.x / viewport_size.x * 2. - 1.,
+            1. - point.y / viewport_size.y * 2.,
+        );
+        view.inverse().project_point3(clip.extend(0.)).truncate()
+    }
+    pub fn skeleton_to_screen(&self, point: Vec2) -> Vec2 {
+        self.world_to_screen(self.skeleton_to_world(point))
+    }
+    pub fn screen_to_skeleton(&self, point: Vec2) -> Vec2 {
+        self.world_to_skeleton(self.screen_to_world(point))
+    }
+}
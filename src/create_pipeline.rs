@@ -1,39 +1,104 @@
+use std::{cell::RefCell, rc::Rc};
+
 use macroquad::{
     miniquad::{
-        BlendState, BufferLayout, Pipeline, RenderingBackend, VertexAttribute, VertexFormat,
+        BufferLayout, CullFace, Pipeline, RenderingBackend, ShaderId, VertexAttribute, VertexFormat,
     },
     prelude::{PipelineParams, ShaderError, ShaderSource},
 };
+use rusty_spine::BlendMode;
 
-use crate::{shader, Spine};
+use crate::{
+    blend_states::{BlendStates, GetBlendStates},
+    shader,
+};
 
-pub(crate) fn create_pipeline(
-    context: &mut dyn RenderingBackend,
-    spine: &Spine,
-    blends: Option<(BlendState, BlendState)>,
-) -> Result<Pipeline, ShaderError> {
-    let source = ShaderSource::Glsl {
-        vertex: shader::VERTEX,
-        fragment: shader::FRAGMENT,
-    };
-    create_pipeline_with_shader(context, spine, blends, source)
+const BLEND_MODES: [BlendMode; 4] = [
+    BlendMode::Normal,
+    BlendMode::Additive,
+    BlendMode::Multiply,
+    BlendMode::Screen,
+];
+
+fn blend_index(blend_mode: BlendMode, premultiplied_alpha: bool) -> usize {
+    blend_mode as usize * 2 + premultiplied_alpha as usize
+}
+
+/// The pipelines for all 8 blend states, for every cull face that has been needed so far.
+///
+/// miniquad can't delete pipelines, so they are only ever created once and then shared by every skeleton.
+pub(crate) struct Pipelines {
+    shader: ShaderId,
+    by_cull_face: Vec<(CullFace, [Pipeline; 8])>,
+}
+
+thread_local! {
+    /// The pipelines of the GL context, which only ever exists on the main thread.
+    static SHARED: RefCell<Option<Rc<RefCell<Pipelines>>>> = const { RefCell::new(None) };
+}
+
+impl Pipelines {
+    /// Returns the pipelines every skeleton shares, compiling the shader the first time.
+    pub(crate) fn shared(
+        context: &mut dyn RenderingBackend,
+    ) -> Result<Rc<RefCell<Self>>, ShaderError> {
+        SHARED.with_borrow_mut(|shared| {
+            if let Some(pipelines) = shared {
+                return Ok(pipelines.clone());
+            }
+            let pipelines = Rc::new(RefCell::new(Self::new(context)?));
+            Ok(shared.insert(pipelines).clone())
+        })
+    }
+    fn new(context: &mut dyn RenderingBackend) -> Result<Self, ShaderError> {
+        let source = ShaderSource::Glsl {
+            vertex: shader::VERTEX,
+            fragment: shader::FRAGMENT,
+        };
+        let shader = context.new_shader(source, shader::meta())?;
+        Ok(Self {
+            shader,
+            by_cull_face: Vec::new(),
+        })
+    }
+    pub(crate) fn get(
+        &mut self,
+        context: &mut dyn RenderingBackend,
+        cull_face: CullFace,
+        blend_mode: BlendMode,
+        premultiplied_alpha: bool,
+    ) -> Pipeline {
+        let index = blend_index(blend_mode, premultiplied_alpha);
+        if let Some((_, pipelines)) = self.by_cull_face.iter().find(|v| v.0 == cull_face) {
+            return pipelines[index];
+        }
+        let mut pipelines = [None; 8];
+        for blend_mode in BLEND_MODES {
+            for premultiplied_alpha in [false, true] {
+                pipelines[blend_index(blend_mode, premultiplied_alpha)] = Some(create_pipeline(
+                    context,
+                    self.shader,
+                    cull_face,
+                    blend_mode.get_blend_states(premultiplied_alpha),
+                ));
+            }
+        }
+        let pipelines = pipelines.map(|v| v.expect("every blend state has a pipeline"));
+        self.by_cull_face.push((cull_face, pipelines));
+        pipelines[index]
+    }
 }
 
-pub(crate) fn create_pipeline_with_shader(
+fn create_pipeline(
     context: &mut dyn RenderingBackend,
-    spine: &Spine,
-    blends: Option<(BlendState, BlendState)>,
-    source: ShaderSource,
-) -> Result<Pipeline, ShaderError> {
-    let shader = context.new_shader(source, shader::meta())?;
-    let mut default_params = PipelineParams::default();
-    default_params.alpha_blend = blends
-        .map(|v| Some(v.0))
-        .unwrap_or(default_params.alpha_blend);
-    default_params.color_blend = blends
-        .map(|v| Some(v.1))
-        .unwrap_or(default_params.color_blend);
-    Ok(context.new_pipeline_with_params(
+    shader: ShaderId,
+    cull_face: CullFace,
+    BlendStates {
+        alpha_blend,
+        color_blend,
+    }: BlendStates,
+) -> Pipeline {
+    context.new_pipeline_with_params(
         &[BufferLayout::default()],
         &[
             VertexAttribute::new("position", VertexFormat::Float2),
@@ -43,8 +108,10 @@ pub(crate) fn create_pipeline_with_shader(
         ],
         shader,
         PipelineParams {
-            cull_face: spine.cull_face,
-            ..default_params
+            cull_face,
+            alpha_blend: Some(alpha_blend),
+            color_blend: Some(color_blend),
+            ..Default::default()
         },
-    ))
+    )
 }
@@ -0,0 +1,143 @@
+use std::{error, fmt};
+
+use macroquad::prelude::ShaderError;
+use rusty_spine::SpineError;
+
+/// Every error that spine_node can return.
+#[derive(Debug)]
+pub enum SpineNodeError {
+    /// A file could not be read.
+    Io { path: String, reason: String },
+    /// The atlas could not be parsed. `path` is `None` if it was loaded from memory.
+    AtlasParse {
+        path: Option<String>,
+        error: SpineError,
+    },
+    /// The skeleton could not be parsed. `path` is `None` if it was loaded from memory.
+    SkeletonParse {
+        path: Option<String>,
+        error: SpineError,
+    },
+    /// The skeleton has no animation with this name.
+    UnknownAnimation { name: String },
+    /// The skeleton has no skin with this name.
+    UnknownSkin { name: String },
+    /// The skeleton has no bone with this name.
+    UnknownBone { name: String },
+    /// The skeleton has no slot with this name.
+    UnknownSlot { name: String },
+    /// The slot has no attachment with this name in the current or default skin.
+    UnknownAttachment { slot: String, name: String },
+    /// The slot does not use two color tinting, so it has no dark color.
+    NoDarkColor { slot: String },
+    /// The skeleton has no constraint of the requested kind with this name.
+    UnknownConstraint { name: String },
+    /// The shader or pipeline used to render the skeleton could not be created.
+    Pipeline(ShaderError),
+    /// Any other error from rusty_spine, like a name containing a nul byte.
+    Spine(SpineError),
+    /// No texture could be found for an atlas page.
+    MissingTexture { page: String, path: String },
+    /// The global [crate::SpineRuntime] was already created, so it can't be created again with different options.
+    RuntimeAlreadyCreated,
+    /// A [crate::SpineState] was given a different [crate::SpineRuntime] than its skeleton was loaded with.
+    RuntimeMismatch,
+}
+
+impl SpineNodeError {
+    pub(crate) fn new_atlas(error: SpineError, path: Option<&str>) -> Self {
+        match error {
+            SpineError::FailedToReadFile { file } => Self::Io {
+                path: file,
+                reason: "Could not read atlas".to_string(),
+            },
+            error => Self::AtlasParse {
+                path: path.map(ToOwned::to_owned),
+                error,
+            },
+        }
+    }
+    pub(crate) fn new_animation(error: SpineError, name: &str) -> Self {
+        match error {
+            SpineError::NotFound { .. } => Self::UnknownAnimation {
+                name: name.to_string(),
+            },
+            error => Self::Spine(error),
+        }
+    }
+    pub(crate) fn new_skin(error: SpineError, name: &str) -> Self {
+        match error {
+            SpineError::NotFound { .. } => Self::UnknownSkin {
+                name: name.to_string(),
+            },
+            error => Self::Spine(error),
+        }
+    }
+    pub(crate) fn new_skeleton(error: SpineError, path: Option<&str>) -> Self {
+        match error {
+            SpineError::FailedToReadFile { file } => Self::Io {
+                path: file,
+                reason: "Could not read skeleton".to_string(),
+            },
+            error => Self::SkeletonParse {
+                path: path.map(ToOwned::to_owned),
+                error,
+            },
+        }
+    }
+}
+
+impl From<ShaderError> for SpineNodeError {
+    fn from(err: ShaderError) -> Self {
+        Self::Pipeline(err)
+    }
+}
+
+impl fmt::Display for SpineNodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, reason } => write!(f, "Failed to read {path}: {reason}"),
+            Self::AtlasParse { path, error } => match path {
+                Some(path) => write!(f, "Failed to parse atlas {path}: {error}"),
+                None => write!(f, "Failed to parse atlas: {error}"),
+            },
+            Self::SkeletonParse { path, error } => match path {
+                Some(path) => write!(f, "Failed to parse skeleton {path}: {error}"),
+                None => write!(f, "Failed to parse skeleton: {error}"),
+            },
+            Self::UnknownAnimation { name } => write!(f, "Unknown animation: {name}"),
+            Self::UnknownSkin { name } => write!(f, "Unknown skin: {name}"),
+            Self::UnknownBone { name } => write!(f, "Unknown bone: {name}"),
+            Self::UnknownSlot { name } => write!(f, "Unknown slot: {name}"),
+            Self::UnknownAttachment { slot, name } => {
+                write!(f, "Unknown attachment {name} for slot {slot}")
+            }
+            Self::NoDarkColor { slot } => write!(f, "Slot {slot} has no dark color"),
+            Self::UnknownConstraint { name } => write!(f, "Unknown constraint: {name}"),
+            Self::Pipeline(error) => write!(f, "Failed to create pipeline: {error}"),
+            Self::Spine(error) => write!(f, "{error}"),
+            Self::MissingTexture { page, path } => {
+                write!(f, "Missing texture for atlas page {page} at {path}")
+            }
+            Self::RuntimeMismatch => write!(
+                f,
+                "The Spine runtime differs from the one the skeleton was loaded with"
+            ),
+            Self::RuntimeAlreadyCreated => {
+                write!(f, "The global Spine runtime was already created")
+            }
+        }
+    }
+}
+
+impl error::Error for SpineNodeError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::AtlasParse { error, .. }
+            | Self::SkeletonParse { error, .. }
+            | Self::Spine(error) => Some(error),
+            Self::Pipeline(error) => Some(error),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use macroquad::{
+    math::Rect,
+    prelude::{vec2, Vec2},
+    texture::Texture2D,
+};
+use rusty_spine::controller::{SkeletonController, SkeletonRenderable};
+
+use crate::{
+    bounds::{corners, SkeletonBounds},
+    c_helpers::{c_array, c_string},
+    setup_rusty_spine::PageTexture,
+    slots::attachment_key,
+    SpineState,
+};
+
+/// A part of a skeleton that got hit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hit {
+    pub slot: String,
+    pub attachment: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HitTestMode {
+    /// Only the bounding box attachments that are currently visible can be hit.
+    BoundingBoxes,
+    /// Everything that gets drawn can be hit.
+    ///
+    /// When testing a point, fully transparent pixels of the texture don't count as a hit.
+    Triangles,
+}
+
+/// Either a point or a (possibly rotated) rectangle, in skeleton space.
+enum Shape {
+    Point(Vec2),
+    Quad([Vec2; 4]),
+}
+
+fn polygon_contains(polygon: &[Vec2], point: Vec2) -> bool {
+    let mut inside = false;
+    let mut previous = match polygon.last() {
+        Some(x) => *x,
+        None => return false,
+    };
+    for &current in polygon {
+        if (current.y < point.y) != (previous.y < point.y)
+            && point.x
+                < (previous.x - current.x) * (point.y - current.y) / (previous.y - current.y)
+                    + current.x
+        {
+            inside = !inside;
+        }
+        previous = current;
+    }
+    inside
+}
+
+fn segments_intersect(a1: Vec2, a2: Vec2, b1: Vec2, b2: Vec2) -> bool {
+    let d1 = (a2 - a1).perp_dot(b1 - a1);
+    let d2 = (a2 - a1).perp_dot(b2 - a1);
+    let d3 = (b2 - b1).perp_dot(a1 - b1);
+    let d4 = (b2 - b1).perp_dot(a2 - b1);
+    d1 * d2 <= 0. && d3 * d4 <= 0.
+}
+
+fn edges(polygon: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    polygon
+        .iter()
+        .copied()
+        .zip(polygon.iter().copied().cycle().skip(1))
+}
+
+fn polygons_intersect(a: &[Vec2], b: &[Vec2]) -> bool {
+    a.iter().any(|&point| polygon_contains(b, point))
+        || b.iter().any(|&point| polygon_contains(a, point))
+        || edges(a).any(|(a1, a2)| edges(b).any(|(b1, b2)| segments_intersect(a1, a2, b1, b2)))
+}
+
+/// Interpolates the uvs of a triangle at a point, using the barycentric coordinates of the point.
+///
+/// Returns `None` if the triangle has no area.
+fn triangle_uv(points: [Vec2; 3], uvs: [Vec2; 3], point: Vec2) -> Option<Vec2> {
+    let [a, b, c] = points;
+    let area = (b - a).perp_dot(c - a);
+    if area == 0. {
+        return None;
+    }
+    let weight_b = (point - a).perp_dot(c - a) / area;
+    let weight_c = (b - a).perp_dot(point - a) / area;
+    let weight_a = 1. - weight_b - weight_c;
+    let [uv_a, uv_b, uv_c] = uvs;
+    Some(uv_a * weight_a + uv_b * weight_b + uv_c * weight_c)
+}
+
+impl Shape {
+    fn from_rect(rect: Rect, to_skeleton: impl Fn(Vec2) -> Vec2) -> Self {
+        let [top_left, top_right, bottom_left, bottom_right] = corners(rect);
+        Self::Quad([top_left, top_right, bottom_right, bottom_left].map(to_skeleton))
+    }
+    fn hits(&self, polygon: &[Vec2]) -> bool {
+        match self {
+            Shape::Point(point) => polygon_contains(polygon, *point),
+            Shape::Quad(quad) => polygons_intersect(polygon, quad),
+        }
+    }
+}
+
+impl SpineState {
+    fn hit_test_shape(&self, shape: Shape, mode: HitTestMode) -> Vec<Hit> {
+        let spine = self.spine.borrow();
+        let mut controller = spine.controller.borrow_mut();
+        match mode {
+            HitTestMode::BoundingBoxes => bounding_box_hits(&controller, &shape),
+            HitTestMode::Triangles => self.triangle_hits(&mut controller, &shape),
+        }
+    }
+    fn triangle_hits(&self, controller: &mut SkeletonController, shape: &Shape) -> Vec<Hit> {
+        // The combined renderables lose track of which slot they belong to, so use the slower ones
+        let renderables = controller.renderables();
+        renderables
+            .iter()
+            .rev()
+            .filter(|renderable| renderable.color.a > 0.)
+            .filter(|renderable| {
+                renderable.indices.chunks_exact(3).any(|triangle| {
+                    let triangle = [
+                        triangle[0] as usize,
+                        triangle[1] as usize,
+                        triangle[2] as usize,
+                    ];
+                    let points = triangle.map(|i| {
+                        let [x, y] = renderable.vertices[i];
+                        vec2(x, y)
+                    });
+                    if !shape.hits(&points) {
+                        return false;
+                    }
+                    match shape {
+                        Shape::Point(point) => self
+                            .texture_alpha(renderable, triangle, points, *point)
+                            .is_none_or(|alpha| alpha > 0.),
+                        Shape::Quad(_) => true,
+                    }
+                })
+            })
+            .filter_map(|renderable| {
+                let slot = controller
+                    .skeleton
+                    .draw_order_at_index(renderable.slot_index)?;
+                let attachment =
+                    unsafe { attachment_key(controller.skeleton.c_ptr(), slot.c_ptr()) }?;
+                Some(Hit {
+                    slot: slot.data().name().to_string(),
+                    attachment,
+                })
+            })
+            .collect()
+    }
+    /// Samples the alpha of the texture at a point inside of a triangle of the renderable.
+    ///
+    /// Returns `None` if the texture isn't available.
+    fn texture_alpha(
+        &self,
+        renderable: &SkeletonRenderable,
+        triangle: [usize; 3],
+        points: [Vec2; 3],
+        point: Vec2,
+    ) -> Option<f32> {
+        let renderer_object = renderable.attachment_renderer_object?;
+        if renderer_object.is_null() {
+            return None;
+        }
+        let page = unsafe { PageTexture::from_renderer_object(renderer_object) };
+        let path = page.state.get_value()?.to_owned();
+        // The copy is outdated once the texture at the path got replaced or disposed
+        let generation = page.runtime.texture_generation(&path)?;
+        let mut texture_alpha = self.texture_alpha.borrow_mut();
+        if !matches!(texture_alpha.get(&path), Some((cached, _)) if *cached == generation) {
+            let image = page
+                .runtime
+                .get_cache_item(&path, |v| v.map(Texture2D::get_texture_data))?;
+            texture_alpha.insert(path.clone(), (generation, image));
+        }
+        let (_, image) = &texture_alpha[&path];
+
+        let uvs = triangle.map(|i| Vec2::from(renderable.uvs[i]));
+        let uv = triangle_uv(points, uvs, point)?;
+
+        let x = (uv.x * image.width() as f32).clamp(0., image.width() as f32 - 1.);
+        let y = (uv.y * image.height() as f32).clamp(0., image.height() as f32 - 1.);
+        Some(image.get_pixel(x as u32, y as u32).a)
+    }
+    /// Finds every part of the skeleton at a point in skeleton space, the one drawn on top first.
+    pub fn hit_test(&self, point: Vec2, mode: HitTestMode) -> Vec<Hit> {
+        self.hit_test_shape(Shape::Point(point), mode)
+    }
+    /// Same as [SpineState::hit_test] but with a point in world space.
+    pub fn hit_test_world(&self, point: Vec2, mode: HitTestMode) -> Vec<Hit> {
+        self.hit_test(self.world_to_skeleton(point), mode)
+    }
+    /// Same as [SpineState::hit_test] but with a point in screen space, like the mouse position.
+    pub fn hit_test_screen(&self, point: Vec2, mode: HitTestMode) -> Vec<Hit> {
+        self.hit_test(self.screen_to_skeleton(point), mode)
+    }
+    /// Finds every part of the skeleton that overlaps a rect in skeleton space, the one drawn on top first.
+    pub fn hit_test_rect(&self, rect: Rect, mode: HitTestMode) -> Vec<Hit> {
+        self.hit_test_shape(Shape::from_rect(rect, |v| v), mode)
+    }
+    /// Same as [SpineState::hit_test_rect] but with a rect in world space.
+    pub fn hit_test_rect_world(&self, rect: Rect, mode: HitTestMode) -> Vec<Hit> {
+        let shape = Shape::from_rect(rect, |v| self.world_to_skeleton(v));
+        self.hit_test_shape(shape, mode)
+    }
+    /// Same as [SpineState::hit_test_rect] but with a rect in screen space.
+    pub fn hit_test_rect_screen(&self, rect: Rect, mode: HitTestMode) -> Vec<Hit> {
+        let shape = Shape::from_rect(rect, |v| self.screen_to_skeleton(v));
+        self.hit_test_shape(shape, mode)
+    }
+}
+
+fn bounding_box_hits(controller: &SkeletonController, shape: &Shape) -> Vec<Hit> {
+    let bounds = SkeletonBounds::new(&controller.skeleton);
+    unsafe {
+        let c_skeleton = &*controller.skeleton.c_ptr();
+        c_array(c_skeleton.drawOrder, c_skeleton.slotsCount)
+            .iter()
+            .rev()
+            .filter_map(|&slot| {
+                let attachment = (*slot).attachment;
+                let polygon = bounds.polygon(attachment)?;
+                shape.hits(&polygon).then(|| Hit {
+                    slot: c_string((*(*slot).data).name),
+                    attachment: attachment_key(c_skeleton, slot).unwrap_or_default(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad::prelude::{vec2, Vec2};
+
+    use super::{polygon_contains, polygons_intersect, segments_intersect, triangle_uv};
+
+    fn square() -> [Vec2; 4] {
+        [vec2(0., 0.), vec2(2., 0.), vec2(2., 2.), vec2(0., 2.)]
+    }
+
+    /// A square with sides of 2 rotated by 45 degrees, centered at `center`.
+    fn diamond(center: Vec2) -> [Vec2; 4] {
+        let r = std::f32::consts::SQRT_2;
+        [vec2(0., -r), vec2(r, 0.), vec2(0., r), vec2(-r, 0.)].map(|v| v + center)
+    }
+
+    #[test]
+    fn points_in_polygons() {
+        assert!(polygon_contains(&square(), vec2(1., 1.)));
+        assert!(!polygon_contains(&square(), vec2(3., 1.)));
+        assert!(!polygon_contains(&square(), vec2(1., -0.5)));
+        // Inside the bounding box of the diamond, but outside of the diamond itself
+        assert!(polygon_contains(&diamond(Vec2::ZERO), vec2(0.5, 0.5)));
+        assert!(!polygon_contains(&diamond(Vec2::ZERO), vec2(1.2, 1.2)));
+        assert!(!polygon_contains(&[], Vec2::ZERO));
+    }
+
+    #[test]
+    fn crossing_segments() {
+        let (a1, a2) = (vec2(0., 0.), vec2(2., 2.));
+        assert!(segments_intersect(a1, a2, vec2(0., 2.), vec2(2., 0.)));
+        assert!(!segments_intersect(a1, a2, vec2(1., 0.), vec2(3., 2.)));
+        assert!(!segments_intersect(a1, a2, vec2(3., 0.), vec2(3., 3.)));
+        // Touching at an end counts
+        assert!(segments_intersect(a1, a2, vec2(2., 2.), vec2(4., 0.)));
+    }
+
+    #[test]
+    fn overlapping_polygons() {
+        let inner = [
+            vec2(0.5, 0.5),
+            vec2(1.5, 0.5),
+            vec2(1.5, 1.5),
+            vec2(0.5, 1.5),
+        ];
+        assert!(polygons_intersect(&square(), &inner));
+        assert!(polygons_intersect(&inner, &square()));
+        assert!(!polygons_intersect(
+            &square(),
+            &inner.map(|v| v + vec2(5., 0.))
+        ));
+        // Only the edges cross, no corner is inside of the other polygon
+        let wide = [vec2(-1., 0.5), vec2(3., 0.5), vec2(3., 1.5), vec2(-1., 1.5)];
+        assert!(polygons_intersect(&square(), &wide));
+        // The corner of the diamond pokes into the square
+        assert!(polygons_intersect(&square(), &diamond(vec2(3., 1.))));
+        // The bounding boxes overlap, but the diamond stays clear of the square
+        assert!(!polygons_intersect(&square(), &diamond(vec2(3.3, 3.3))));
+    }
+
+    #[test]
+    fn uvs_inside_of_triangles() {
+        let points = [vec2(0., 0.), vec2(2., 0.), vec2(0., 2.)];
+        let uvs = [vec2(0., 1.), vec2(1., 1.), vec2(0., 0.)];
+        for (point, uv) in points.into_iter().zip(uvs) {
+            assert_eq!(triangle_uv(points, uvs, point), Some(uv));
+        }
+        assert_eq!(triangle_uv(points, uvs, vec2(1., 0.)), Some(vec2(0.5, 1.)));
+        assert_eq!(
+            triangle_uv(points, uvs, vec2(0.5, 0.5)),
+            Some(vec2(0.25, 0.75))
+        );
+        let line = [vec2(0., 0.), vec2(1., 1.), vec2(2., 2.)];
+        assert_eq!(triangle_uv(line, uvs, vec2(1., 1.)), None);
+    }
+}
@@ -0,0 +1,174 @@
+use macroquad::prelude::Vec2;
+use rusty_spine::{
+    c::{spBone_worldToLocal, spIkConstraint},
+    Skeleton,
+};
+
+use crate::{
+    c_helpers::{c_array, c_string},
+    Spine, SpineNodeError, SpineState,
+};
+
+/// The current state of an IK constraint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IkConstraintInfo {
+    pub name: String,
+    /// The bones that get rotated to reach the target.
+    pub bones: Vec<String>,
+    pub target: String,
+    pub mix: f32,
+    pub softness: f32,
+    /// Either 1 or -1.
+    pub bend_direction: i32,
+    pub compress: bool,
+    pub stretch: bool,
+    pub active: bool,
+}
+
+/// Finds an IK constraint by name.
+///
+/// # Safety
+///
+/// The returned pointer is only valid for as long as the skeleton is.
+unsafe fn find_ik_constraint(skeleton: &Skeleton, name: &str) -> Option<*mut spIkConstraint> {
+    let c_skeleton = &*skeleton.c_ptr();
+    c_array(c_skeleton.ikConstraints, c_skeleton.ikConstraintsCount)
+        .iter()
+        .copied()
+        .find(|&constraint| c_string((*(*constraint).data).name) == name)
+}
+
+impl Spine {
+    /// Moves the target bones of IK constraints with a set target to that target.
+    ///
+    /// This needs the world transforms of the parents of the target bones, so it updates them first.
+    pub(crate) fn apply_ik_targets(&self, skeleton: &mut Skeleton) {
+        if self.ik_targets.is_empty() {
+            return;
+        }
+        skeleton.update_world_transform();
+        for (name, target) in &self.ik_targets {
+            // Targets converted through a view or skeleton that is scaled to zero
+            if !target.is_finite() {
+                continue;
+            }
+            unsafe {
+                let Some(constraint) = find_ik_constraint(skeleton, name) else {
+                    continue;
+                };
+                let bone = (*constraint).target;
+                let parent = (*bone).parent;
+                if parent.is_null() {
+                    // A skeleton scaled to zero on an axis can't be reached on that axis
+                    let c_skeleton = &*skeleton.c_ptr();
+                    if c_skeleton.scaleX != 0. {
+                        (*bone).x = (target.x - c_skeleton.x) / c_skeleton.scaleX;
+                    }
+                    if c_skeleton.scaleY != 0. {
+                        (*bone).y = (target.y - c_skeleton.y) / c_skeleton.scaleY;
+                    }
+                } else if (*parent).a * (*parent).d - (*parent).b * (*parent).c != 0. {
+                    spBone_worldToLocal(parent, target.x, target.y, &mut (*bone).x, &mut (*bone).y);
+                }
+            }
+        }
+    }
+}
+
+impl SpineState {
+    fn with_ik_constraint<T>(
+        &self,
+        name: &str,
+        cb: impl FnOnce(&mut spIkConstraint) -> T,
+    ) -> Result<T, SpineNodeError> {
+        let spine = self.spine.borrow();
+        let controller = spine.controller.borrow_mut();
+        let constraint =
+            unsafe { find_ik_constraint(&controller.skeleton, name) }.ok_or_else(|| {
+                SpineNodeError::UnknownConstraint {
+                    name: name.to_string(),
+                }
+            })?;
+        Ok(cb(unsafe { &mut *constraint }))
+    }
+    pub fn ik_constraints(&self) -> Vec<IkConstraintInfo> {
+        let spine = self.spine.borrow();
+        let controller = spine.controller.borrow();
+        unsafe {
+            let c_skeleton = &*controller.skeleton.c_ptr();
+            c_array(c_skeleton.ikConstraints, c_skeleton.ikConstraintsCount)
+                .iter()
+                .map(|&constraint| {
+                    let constraint = &*constraint;
+                    IkConstraintInfo {
+                        name: c_string((*constraint.data).name),
+                        bones: c_array(constraint.bones, constraint.bonesCount)
+                            .iter()
+                            .map(|&bone| c_string((*(*bone).data).name))
+                            .collect(),
+                        target: c_string((*(*constraint.target).data).name),
+                        mix: constraint.mix,
+                        softness: constraint.softness,
+                        bend_direction: constraint.bendDirection,
+                        compress: constraint.compress != 0,
+                        stretch: constraint.stretch != 0,
+                        active: constraint.active != 0,
+                    }
+                })
+                .collect()
+        }
+    }
+    /// Sets how much the constraint affects its bones, from 0 to 1.
+    ///
+    /// Animations that key this constraint will overwrite this.
+    pub fn set_ik_mix(&mut self, name: &str, mix: f32) -> Result<(), SpineNodeError> {
+        self.with_ik_constraint(name, |constraint| constraint.mix = mix)
+    }
+    /// Sets the distance from the target at which the bones start to straighten out.
+    ///
+    /// Animations that key this constraint will overwrite this.
+    pub fn set_ik_softness(&mut self, name: &str, softness: f32) -> Result<(), SpineNodeError> {
+        self.with_ik_constraint(name, |constraint| constraint.softness = softness)
+    }
+    /// Sets the direction two bone constraints bend in. Negative values bend in the opposite direction.
+    ///
+    /// Animations that key this constraint will overwrite this.
+    pub fn set_ik_bend_direction(
+        &mut self,
+        name: &str,
+        bend_direction: i32,
+    ) -> Result<(), SpineNodeError> {
+        self.with_ik_constraint(name, |constraint| {
+            constraint.bendDirection = if bend_direction < 0 { -1 } else { 1 }
+        })
+    }
+    /// Moves the target bone of the constraint to a point in skeleton space every frame,
+    /// after the animations and bone overrides are applied.
+    ///
+    /// The target stays active until [SpineState::clear_ik_target] is called.
+    pub fn set_ik_target(&mut self, name: &str, point: Vec2) -> Result<(), SpineNodeError> {
+        self.with_ik_constraint(name, |_| ())?;
+        self.spine
+            .borrow_mut()
+            .ik_targets
+            .insert(name.to_string(), point);
+        Ok(())
+    }
+    /// Same as [SpineState::set_ik_target] but with a point in world space.
+    pub fn set_ik_target_world(&mut self, name: &str, point: Vec2) -> Result<(), SpineNodeError> {
+        let point = self.world_to_skeleton(point);
+        self.set_ik_target(name, point)
+    }
+    /// Same as [SpineState::set_ik_target] but with a point in screen space, like the mouse position.
+    pub fn set_ik_target_screen(&mut self, name: &str, point: Vec2) -> Result<(), SpineNodeError> {
+        let point = self.screen_to_skeleton(point);
+        self.set_ik_target(name, point)
+    }
+    /// Gives control over the target bone back to the animations.
+    pub fn clear_ik_target(&mut self, name: &str) {
+        self.spine.borrow_mut().ik_targets.remove(name);
+    }
+    pub fn clear_ik_targets(&mut self) {
+        self.spine.borrow_mut().ik_targets.clear();
+    }
+}
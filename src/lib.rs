@@ -1,28 +1,59 @@
 mod animation_event;
 mod blend_states;
+mod bones;
+mod bounds;
+mod c_helpers;
+mod camera;
+mod constraints;
+mod coordinates;
 mod create_pipeline;
+mod error;
+mod hit_test;
+mod ik_constraints;
+mod render_buffers;
 mod setup_rusty_spine;
 mod shader;
+mod skeleton_info;
+mod slots;
 mod spine;
+mod spine_asset;
 mod spine_component;
+mod tint;
 
 use std::borrow::Cow;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::rc::Rc;
 
 pub use animation_event::AnimationEvent;
-use create_pipeline::create_pipeline;
-use macroquad::miniquad::{Bindings, Pipeline};
-use macroquad::prelude::Mat4;
-use macroquad::prelude::ShaderError;
-use macroquad::window::get_internal_gl;
-use monad_quad::components::asyncs::AsyncState;
+pub use bones::{BoneOverride, BoneTransform, BoneWorldTransform};
+pub use bounds::BoundsSource;
+pub use camera::SpineCamera;
+pub use constraints::{
+    PathConstraintInfo, PathConstraintMix, TransformConstraintInfo, TransformConstraintMix,
+};
+use coordinates::ScreenTransform;
+use create_pipeline::Pipelines;
+pub use error::SpineNodeError;
+pub use hit_test::{Hit, HitTestMode};
+pub use ik_constraints::IkConstraintInfo;
+use macroquad::prelude::{Mat4, Vec2};
+use macroquad::texture::Image;
+use macroquad::window::{get_internal_gl, screen_height, screen_width};
 use monad_quad::components::Context;
+use render_buffers::RenderBuffers;
 use rusty_spine::c_interface::CTmpMut;
-use rusty_spine::{Animation, AnimationState, Skeleton, Skin, SpineError, TrackEntry};
-pub use setup_rusty_spine::{setup_runtime, unchecked_assume_runtime_created, Token};
-pub use spine::{AnimationOptions, LoadSpineFromBytes, LoadSpineFromFile, Spine};
+use rusty_spine::{Animation, AnimationState, BlendMode, Skeleton, Skin, TrackEntry};
+use setup_rusty_spine::PageTexture;
+pub use setup_rusty_spine::{
+    setup_runtime, setup_runtime_with, unchecked_assume_runtime_created, DefaultTextureProvider,
+    FallbackTexture, ProvidedTexture, SpineRuntime, SpineRuntimeOptions, TextureCacheStats,
+    TextureError, TexturePage, TextureProvider, Token,
+};
+pub use skeleton_info::{AnimationInfo, BoneInfo, EventInfo, SkeletonInfo, SlotInfo};
+pub use spine::{AnimationMix, AnimationOptions, LoadSpineFromBytes, LoadSpineFromFile, Spine};
+pub use spine_asset::SpineAsset;
 pub use spine_component::{AnimationStateWithData, SpineAnimation};
 
 #[derive(Clone)]
@@ -36,19 +67,49 @@ pub enum SpineSkeletonBytes<'a> {
     Json(Cow<'a, [u8]>),
 }
 
+/// How far along loading the textures of a skeleton is, see [SpineState::texture_load_status].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureLoadStatus {
+    /// Some textures are still loading.
+    Loading,
+    /// Every texture is loaded.
+    Loaded,
+    /// Every texture is done loading, but some failed to and use a fallback or nothing at all.
+    /// See [SpineState::texture_errors] for which ones.
+    LoadedWithErrors,
+}
+
 #[derive(Clone)]
 pub struct SpineState {
     spine: RefCell<Spine>,
-    pipeline: Pipeline,
-    bindings: RefCell<Vec<Bindings>>,
-    texture_delete_queue: Token,
+    pipelines: Rc<RefCell<Pipelines>>,
+    render_buffers: RefCell<RenderBuffers>,
+    runtime: SpineRuntime,
+    camera: SpineCamera,
+    screen: Cell<ScreenTransform>,
+    /// Copies of the textures used for hit testing together with their generation, by texture path.
+    texture_alpha: RefCell<HashMap<PathBuf, (u64, Image)>>,
     pub(crate) events: Rc<RefCell<Vec<AnimationEvent>>>,
 }
 
 impl SpineState {
-    pub fn new(spine: Spine, texture_delete_queue: Token) -> Result<Self, ShaderError> {
+    /// Turns a [Spine] into something that can be rendered.
+    ///
+    /// `runtime` has to be the runtime the skeleton was loaded with,
+    /// otherwise [SpineNodeError::RuntimeMismatch] is returned.
+    pub fn new(spine: Spine, runtime: SpineRuntime) -> Result<Self, SpineNodeError> {
+        if !runtime.ptr_eq(&spine.runtime) {
+            return Err(SpineNodeError::RuntimeMismatch);
+        }
         let context = unsafe { get_internal_gl() };
-        let pipeline = create_pipeline(context.quad_context, &spine, None)?;
+        let pipelines = Pipelines::shared(context.quad_context)?;
+        // Create the pipelines of this cull face now instead of during the first render
+        pipelines.borrow_mut().get(
+            context.quad_context,
+            spine.effective_cull_face(),
+            BlendMode::Normal,
+            false,
+        );
         let events = Rc::new(RefCell::new(Vec::new()));
         let moved_events = events.clone();
         spine
@@ -59,30 +120,44 @@ impl SpineState {
         Ok(Self {
             events,
             spine: RefCell::new(spine),
-            pipeline,
-            bindings: RefCell::new(vec![]),
-            texture_delete_queue,
+            pipelines,
+            render_buffers: RefCell::new(RenderBuffers::default()),
+            runtime,
+            texture_alpha: RefCell::new(HashMap::new()),
+            camera: SpineCamera::default(),
+            screen: Cell::new(ScreenTransform::centered(Vec2::new(
+                screen_width(),
+                screen_height(),
+            ))),
         })
     }
-    pub fn new_spine(self, spine: Spine) -> Result<Self, ShaderError> {
-        Self::new(spine, self.texture_delete_queue)
+    pub fn new_spine(self, spine: Spine) -> Result<Self, SpineNodeError> {
+        let runtime = spine.runtime.clone();
+        let mut state = Self::new(spine, runtime)?;
+        state.set_camera(self.camera);
+        Ok(state)
     }
+    /// Loads a skeleton together with every texture it needs using [Spine::load_async]
+    /// and turns it into a [SpineState] that is ready to be rendered.
+    pub async fn load_async(
+        info: LoadSpineFromFile<'_>,
+        runtime: SpineRuntime,
+    ) -> Result<Self, SpineNodeError> {
+        let spine = Spine::load_async(info, runtime.clone()).await?;
+        Self::new(spine, runtime)
+    }
+    /// Calculates the view matrix and remembers it for the coordinate conversions.
     fn view(&self, context: &Context) -> Mat4 {
-        Mat4::orthographic_rh_gl(
-            context.viewport_size().x * -0.5,
-            context.viewport_size().x * 0.5,
-            context.viewport_size().y * -0.5,
-            context.viewport_size().y * 0.5,
-            0.,
-            1.,
-        )
+        let screen = ScreenTransform::from_camera(&self.camera, context.viewport_size());
+        self.screen.set(screen);
+        screen.view
     }
     pub fn set_animation_by_name(
         &mut self,
         track_index: usize,
         animation_name: &str,
         looping: bool,
-    ) -> Result<(), SpineError> {
+    ) -> Result<(), SpineNodeError> {
         self.set_animation_by_name_with_cb(track_index, animation_name, looping, |x| x.map(|_| ()))
     }
     pub fn set_animation_by_name_with_cb<T, E>(
@@ -90,15 +165,14 @@ impl SpineState {
         track_index: usize,
         animation_name: &str,
         looping: bool,
-        cb: impl FnOnce(Result<CTmpMut<AnimationState, TrackEntry>, SpineError>) -> Result<T, E>,
+        cb: impl FnOnce(Result<CTmpMut<AnimationState, TrackEntry>, SpineNodeError>) -> Result<T, E>,
     ) -> Result<T, E> {
         let spine = self.spine.borrow();
         let mut borrowed_controller = spine.controller.borrow_mut();
-        let x = borrowed_controller.animation_state.set_animation_by_name(
-            track_index,
-            animation_name,
-            looping,
-        );
+        let x = borrowed_controller
+            .animation_state
+            .set_animation_by_name(track_index, animation_name, looping)
+            .map_err(|e| SpineNodeError::new_animation(e, animation_name));
         cb(x)
     }
     pub fn set_animation(&mut self, track_index: usize, animation: &Animation, looping: bool) {
@@ -118,10 +192,168 @@ impl SpineState {
             .set_animation(track_index, animation, looping);
         cb(x)
     }
-    pub fn set_skin_by_name(&mut self, skin_name: &str) -> Result<(), SpineError> {
+    pub fn add_animation_by_name(
+        &mut self,
+        track_index: usize,
+        animation_name: &str,
+        looping: bool,
+        delay: f32,
+    ) -> Result<(), SpineNodeError> {
+        self.add_animation_by_name_with_cb(track_index, animation_name, looping, delay, |x| {
+            x.map(|_| ())
+        })
+    }
+    pub fn add_animation_by_name_with_cb<T, E>(
+        &mut self,
+        track_index: usize,
+        animation_name: &str,
+        looping: bool,
+        delay: f32,
+        cb: impl FnOnce(Result<CTmpMut<AnimationState, TrackEntry>, SpineNodeError>) -> Result<T, E>,
+    ) -> Result<T, E> {
+        let spine = self.spine.borrow();
+        let mut borrowed_controller = spine.controller.borrow_mut();
+        let x = borrowed_controller
+            .animation_state
+            .add_animation_by_name(track_index, animation_name, looping, delay)
+            .map_err(|e| SpineNodeError::new_animation(e, animation_name));
+        cb(x)
+    }
+    pub fn add_animation(
+        &mut self,
+        track_index: usize,
+        animation: &Animation,
+        looping: bool,
+        delay: f32,
+    ) {
+        self.add_animation_with_cb(track_index, animation, looping, delay, |_| ())
+    }
+    pub fn add_animation_with_cb<T>(
+        &mut self,
+        track_index: usize,
+        animation: &Animation,
+        looping: bool,
+        delay: f32,
+        cb: impl FnOnce(CTmpMut<AnimationState, TrackEntry>) -> T,
+    ) -> T {
+        let spine = self.spine.borrow();
+        let mut borrowed_controller = spine.controller.borrow_mut();
+        let x = borrowed_controller.animation_state.add_animation(
+            track_index,
+            animation,
+            looping,
+            delay,
+        );
+        cb(x)
+    }
+    /// Mixes out the current animation of the track over `mix_duration` seconds,
+    /// clearing anything that was queued.
+    pub fn set_empty_animation(&mut self, track_index: usize, mix_duration: f32) {
+        self.set_empty_animation_with_cb(track_index, mix_duration, |_| ())
+    }
+    pub fn set_empty_animation_with_cb<T>(
+        &mut self,
+        track_index: usize,
+        mix_duration: f32,
+        cb: impl FnOnce(CTmpMut<AnimationState, TrackEntry>) -> T,
+    ) -> T {
+        let spine = self.spine.borrow();
+        let mut borrowed_controller = spine.controller.borrow_mut();
+        let x = borrowed_controller
+            .animation_state
+            .set_empty_animation(track_index, mix_duration);
+        cb(x)
+    }
+    /// Queues mixing out the animation of the track over `mix_duration` seconds after `delay` seconds.
+    pub fn add_empty_animation(&mut self, track_index: usize, mix_duration: f32, delay: f32) {
+        self.add_empty_animation_with_cb(track_index, mix_duration, delay, |_| ())
+    }
+    pub fn add_empty_animation_with_cb<T>(
+        &mut self,
+        track_index: usize,
+        mix_duration: f32,
+        delay: f32,
+        cb: impl FnOnce(CTmpMut<AnimationState, TrackEntry>) -> T,
+    ) -> T {
+        let spine = self.spine.borrow();
+        let mut borrowed_controller = spine.controller.borrow_mut();
+        let x = borrowed_controller.animation_state.add_empty_animation(
+            track_index,
+            mix_duration,
+            delay,
+        );
+        cb(x)
+    }
+    /// Mixes out the animations of every track over `mix_duration` seconds.
+    pub fn set_empty_animations(&mut self, mix_duration: f32) {
+        let spine = self.spine.borrow();
+        let mut controller = spine.controller.borrow_mut();
+        controller
+            .animation_state
+            .set_empty_animations(mix_duration);
+    }
+    /// Removes the current and queued animations of the track, leaving the skeleton in its current pose.
+    pub fn clear_track(&mut self, track_index: usize) {
+        let spine = self.spine.borrow();
+        let mut controller = spine.controller.borrow_mut();
+        controller.animation_state.clear_track(track_index);
+    }
+    /// Removes the current and queued animations of every track, leaving the skeleton in its current pose.
+    pub fn clear_tracks(&mut self) {
+        let spine = self.spine.borrow();
+        let mut controller = spine.controller.borrow_mut();
+        controller.animation_state.clear_tracks();
+    }
+    /// Sets the mix duration used when no mix is set for a pair of animations.
+    ///
+    /// The mixes are shared with every other instance created from the same [SpineAsset].
+    pub fn set_default_mix(&mut self, duration: f32) {
         let spine = self.spine.borrow();
         let mut controller = spine.controller.borrow_mut();
-        let res = controller.skeleton.set_skin_by_name(skin_name);
+        controller
+            .animation_state
+            .data_mut()
+            .set_default_mix(duration);
+    }
+    pub fn default_mix(&self) -> f32 {
+        let spine = self.spine.borrow();
+        let controller = spine.controller.borrow();
+        controller.animation_state.data().default_mix()
+    }
+    /// Sets how long it takes to crossfade from the animation `from` to the animation `to`.
+    ///
+    /// The mixes are shared with every other instance created from the same [SpineAsset].
+    pub fn set_mix_by_name(
+        &mut self,
+        from: &str,
+        to: &str,
+        duration: f32,
+    ) -> Result<(), SpineNodeError> {
+        self.spine.borrow().set_mix_by_name(from, to, duration)
+    }
+    pub fn get_mix_by_name(&self, from: &str, to: &str) -> Result<f32, SpineNodeError> {
+        let spine = self.spine.borrow();
+        let mut controller = spine.controller.borrow_mut();
+        let controller = &mut *controller;
+        let skeleton_data = controller.skeleton.data();
+        let find_animation = |name: &str| {
+            skeleton_data
+                .find_animation(name)
+                .ok_or_else(|| SpineNodeError::UnknownAnimation {
+                    name: name.to_string(),
+                })
+        };
+        let from = find_animation(from)?;
+        let to = find_animation(to)?;
+        Ok(controller.animation_state.data_mut().get_mix(&from, &to))
+    }
+    pub fn set_skin_by_name(&mut self, skin_name: &str) -> Result<(), SpineNodeError> {
+        let spine = self.spine.borrow();
+        let mut controller = spine.controller.borrow_mut();
+        let res = controller
+            .skeleton
+            .set_skin_by_name(skin_name)
+            .map_err(|e| SpineNodeError::new_skin(e, skin_name));
         controller.skeleton.set_slots_to_setup_pose();
         res
     }
@@ -133,32 +365,88 @@ impl SpineState {
         let controller = spine.controller.borrow_mut();
         cb(controller.skeleton.skin())
     }
-    pub(crate) fn process_loading(&self) {
-        let renderables = self
-            .spine
-            .borrow()
-            .controller
-            .borrow_mut()
-            .combined_renderables();
-        for renderable in renderables {
-            let Some(attachment_renderer_object) = renderable.attachment_renderer_object else { continue };
-            let spine_texture =
-                unsafe { &mut *(attachment_renderer_object as *mut AsyncState<PathBuf>) };
-            spine_texture.process();
+    pub fn set_position(&mut self, position: Vec2) {
+        self.spine.borrow_mut().position = position;
+    }
+    pub fn position(&self) -> Vec2 {
+        self.spine.borrow().position
+    }
+    /// Sets the rotation of the skeleton, in radians.
+    pub fn set_rotation(&mut self, rotation: f32) {
+        self.spine.borrow_mut().rotation = rotation;
+    }
+    /// The rotation of the skeleton, in radians.
+    pub fn rotation(&self) -> f32 {
+        self.spine.borrow().rotation
+    }
+    /// Sets the scale of the skeleton. A negative scale flips the skeleton on that axis.
+    pub fn set_scale(&mut self, scale: Vec2) {
+        self.spine.borrow_mut().scale = scale;
+    }
+    pub fn scale(&self) -> Vec2 {
+        self.spine.borrow().scale
+    }
+    pub fn set_flip_x(&mut self, flip: bool) {
+        let mut spine = self.spine.borrow_mut();
+        spine.scale.x = if flip {
+            -spine.scale.x.abs()
+        } else {
+            spine.scale.x.abs()
+        };
+    }
+    pub fn flip_x(&self) -> bool {
+        self.spine.borrow().scale.x < 0.
+    }
+    pub fn set_flip_y(&mut self, flip: bool) {
+        let mut spine = self.spine.borrow_mut();
+        spine.scale.y = if flip {
+            -spine.scale.y.abs()
+        } else {
+            spine.scale.y.abs()
+        };
+    }
+    pub fn flip_y(&self) -> bool {
+        self.spine.borrow().scale.y < 0.
+    }
+    /// Takes a snapshot of everything the skeleton contains.
+    pub fn skeleton_info(&self) -> SkeletonInfo {
+        let spine = self.spine.borrow();
+        let controller = spine.controller.borrow();
+        SkeletonInfo::from(&*controller.skeleton.data())
+    }
+    /// Calls `f` with the texture of every atlas page of this skeleton, including pages no attachment shows right now.
+    fn for_each_page_texture(&self, mut f: impl FnMut(&mut PageTexture)) {
+        let spine = self.spine.borrow();
+        for page in spine.atlas.pages() {
+            let mut renderer_object = page.renderer_object();
+            if let Some(page_texture) = unsafe { renderer_object.get::<PageTexture>() } {
+                f(page_texture);
+            }
         }
     }
+    pub(crate) fn process_loading(&self) {
+        self.for_each_page_texture(|v| v.state.process());
+    }
+    /// Whether the textures of every atlas page of this skeleton are done loading, including those that failed to.
     pub fn is_fully_loaded(&self) -> bool {
-        self.spine
-            .borrow()
-            .controller
-            .borrow_mut()
-            .combined_renderables()
-            .iter()
-            .all(|v| {
-                let Some(attachment_renderer_object) = v.attachment_renderer_object else { return true };
-                let spine_texture =
-                    unsafe { &mut *(attachment_renderer_object as *mut AsyncState<PathBuf>) };
-                spine_texture.is_loaded()
-            })
+        let mut loaded = true;
+        self.for_each_page_texture(|v| loaded &= v.state.is_loaded());
+        loaded
+    }
+    /// Same as [SpineState::is_fully_loaded], but also tells whether any of the textures failed to load.
+    pub fn texture_load_status(&self) -> TextureLoadStatus {
+        if !self.is_fully_loaded() {
+            TextureLoadStatus::Loading
+        } else if self.texture_errors().is_empty() {
+            TextureLoadStatus::Loaded
+        } else {
+            TextureLoadStatus::LoadedWithErrors
+        }
+    }
+    /// The atlas pages of this skeleton whose texture failed to load.
+    ///
+    /// Pages that are still loading are not included, even if they will fail.
+    pub fn texture_errors(&self) -> Vec<TextureError> {
+        setup_rusty_spine::texture_errors(&self.spine.borrow().atlas)
     }
 }
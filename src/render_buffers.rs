@@ -0,0 +1,268 @@
+use macroquad::{
+    miniquad::{
+        Bindings, BufferSource, BufferType, BufferUsage, Pipeline, RenderingBackend, TextureId,
+        UniformsSource,
+    },
+    prelude::Vec2,
+    window::get_internal_gl,
+};
+use rusty_spine::{
+    c::{
+        spClippingAttachment, spMeshAttachment, spSlotData, SP_ATTACHMENT_CLIPPING,
+        SP_ATTACHMENT_MESH, SP_ATTACHMENT_REGION,
+    },
+    controller::{SkeletonCombinedRenderable, SkeletonController},
+    Color, Skeleton,
+};
+
+use crate::{c_helpers::c_array, shader};
+
+/// The size the buffers start at. They grow when a skeleton needs more.
+const INITIAL_VERTICES: usize = 10000;
+const INITIAL_INDICES: usize = 5000;
+/// Indices are `u16`, so one draw can't use more vertices than this.
+const MAX_INDEXED_VERTICES: usize = u16::MAX as usize + 1;
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct Vertex {
+    position: Vec2,
+    uv: Vec2,
+    color: Color,
+    dark_color: Color,
+}
+
+/// A range of the index buffer that gets drawn with the same pipeline and texture.
+#[derive(Clone, Copy)]
+struct DrawCall {
+    pipeline: Pipeline,
+    texture: TextureId,
+    first_index: usize,
+    index_count: usize,
+}
+
+/// A single vertex and index buffer that every renderable of a skeleton gets drawn from.
+///
+/// Renderables are collected in the staging buffers, which are uploaded all at once
+/// and then drawn as one draw call per renderable.
+/// The staging buffers are kept around, so they don't need to be allocated again every frame.
+#[derive(Default)]
+pub(crate) struct RenderBuffers {
+    bindings: Option<Bindings>,
+    vertex_capacity: usize,
+    index_capacity: usize,
+    vertices: Vec<Vertex>,
+    indices: Vec<u16>,
+    draw_calls: Vec<DrawCall>,
+}
+
+impl RenderBuffers {
+    /// Adds a renderable to the staging buffers.
+    ///
+    /// If its vertices can't be indexed together with what is already staged,
+    /// the staged renderables get drawn first.
+    pub(crate) fn push(
+        &mut self,
+        ctx: &mut dyn RenderingBackend,
+        uniforms: &shader::Uniforms,
+        renderable: &SkeletonCombinedRenderable,
+        pipeline: Pipeline,
+        texture: TextureId,
+    ) {
+        if self.vertices.len() + renderable.vertices.len() > MAX_INDEXED_VERTICES {
+            self.flush(ctx, uniforms);
+        }
+        let first_vertex = self.vertices.len() as u16;
+        let first_index = self.indices.len();
+        self.vertices.extend(
+            renderable
+                .vertices
+                .iter()
+                .zip(&renderable.uvs)
+                .zip(renderable.colors.iter().zip(&renderable.dark_colors))
+                .map(|((&position, &uv), (&color, &dark_color))| Vertex {
+                    position: Vec2::from(position),
+                    uv: Vec2::from(uv),
+                    color: Color::from(color),
+                    dark_color: Color::from(dark_color),
+                }),
+        );
+        self.indices
+            .extend(renderable.indices.iter().map(|&index| first_vertex + index));
+        self.draw_calls.push(DrawCall {
+            pipeline,
+            texture,
+            first_index,
+            index_count: renderable.indices.len(),
+        });
+    }
+    /// Uploads everything that is staged and draws it.
+    pub(crate) fn flush(&mut self, ctx: &mut dyn RenderingBackend, uniforms: &shader::Uniforms) {
+        if self.draw_calls.is_empty() {
+            return;
+        }
+        self.reserve(ctx);
+        let Some(bindings) = &mut self.bindings else {
+            return;
+        };
+        ctx.buffer_update(
+            bindings.vertex_buffers[0],
+            BufferSource::slice(&self.vertices),
+        );
+        ctx.buffer_update(bindings.index_buffer, BufferSource::slice(&self.indices));
+        for draw_call in self.draw_calls.drain(..) {
+            ctx.apply_pipeline(&draw_call.pipeline);
+            bindings.images = vec![draw_call.texture];
+            ctx.apply_bindings(bindings);
+            ctx.apply_uniforms(UniformsSource::table(uniforms));
+            ctx.draw(
+                draw_call.first_index as i32,
+                draw_call.index_count as i32,
+                1,
+            );
+        }
+        self.vertices.clear();
+        self.indices.clear();
+    }
+    /// Makes sure the gpu buffers can hold everything that is staged, replacing them with bigger ones if needed.
+    fn reserve(&mut self, ctx: &mut dyn RenderingBackend) {
+        if self.bindings.is_some()
+            && self.vertices.len() <= self.vertex_capacity
+            && self.indices.len() <= self.index_capacity
+        {
+            return;
+        }
+        self.delete_buffers(ctx);
+        self.vertex_capacity = self
+            .vertex_capacity
+            .max(INITIAL_VERTICES)
+            .max(self.vertices.len().next_power_of_two());
+        self.index_capacity = self
+            .index_capacity
+            .max(INITIAL_INDICES)
+            .max(self.indices.len().next_power_of_two());
+        let buffer_source = BufferSource::empty::<Vertex>(self.vertex_capacity);
+        let vertex_buffer =
+            ctx.new_buffer(BufferType::VertexBuffer, BufferUsage::Stream, buffer_source);
+        let buffer_source = BufferSource::empty::<u16>(self.index_capacity);
+        let index_buffer =
+            ctx.new_buffer(BufferType::IndexBuffer, BufferUsage::Stream, buffer_source);
+        self.bindings = Some(Bindings {
+            vertex_buffers: vec![vertex_buffer],
+            index_buffer,
+            images: vec![],
+        });
+    }
+    fn delete_buffers(&mut self, ctx: &mut dyn RenderingBackend) {
+        if let Some(bindings) = self.bindings.take() {
+            ctx.delete_buffer(bindings.vertex_buffers[0]);
+            ctx.delete_buffer(bindings.index_buffer);
+        }
+    }
+}
+
+/// Clones get their own gpu buffers once they draw, as one clone growing its buffers would delete the ones the others use.
+impl Clone for RenderBuffers {
+    fn clone(&self) -> Self {
+        // Everything staged gets drawn within the frame, so there is nothing worth copying
+        Self::default()
+    }
+}
+
+impl Drop for RenderBuffers {
+    fn drop(&mut self) {
+        // The buffers only exist if something was drawn, so there is a GL context to delete them from
+        if self.bindings.is_some() {
+            let gl = unsafe { get_internal_gl() };
+            self.delete_buffers(gl.quad_context);
+        }
+    }
+}
+
+/// Whether rusty_spine's combined drawer can draw the skeleton without overflowing.
+///
+/// The drawer counts the vertices and indices of every batch in `u16`, which panics or wraps
+/// around once a batch gets bigger than that. Batches are split by texture and blend mode,
+/// so the whole skeleton is an upper bound for every batch. A clipped triangle ends up as one
+/// polygon per convex part of the clipping polygon, which is estimated generously.
+fn fits_combined_drawer(skeleton: &Skeleton) -> bool {
+    let mut vertices = 0;
+    let mut indices = 0;
+    // The vertex count of the active clipping polygon and the slot its clipping ends at
+    let mut clipping: Option<(usize, *mut spSlotData)> = None;
+    unsafe {
+        let c_skeleton = &*skeleton.c_ptr();
+        for &slot in c_array(c_skeleton.drawOrder, c_skeleton.slotsCount) {
+            let attachment = (*slot).attachment;
+            let (slot_vertices, slot_indices) = if attachment.is_null() {
+                (0, 0)
+            } else {
+                match (*attachment).type_0 {
+                    SP_ATTACHMENT_REGION => (4, 6),
+                    SP_ATTACHMENT_MESH => {
+                        let mesh = &*(attachment as *const spMeshAttachment);
+                        (
+                            mesh.super_0.worldVerticesLength as usize / 2,
+                            mesh.trianglesCount as usize,
+                        )
+                    }
+                    SP_ATTACHMENT_CLIPPING => {
+                        let clip = &*(attachment as *const spClippingAttachment);
+                        clipping =
+                            Some((clip.super_0.worldVerticesLength as usize / 2, clip.endSlot));
+                        continue;
+                    }
+                    _ => (0, 0),
+                }
+            };
+            match clipping {
+                Some((clip_vertices, _)) => {
+                    let triangles = slot_indices / 3;
+                    vertices += triangles * 6 * clip_vertices;
+                    indices += triangles * 12 * clip_vertices;
+                }
+                None => {
+                    vertices += slot_vertices;
+                    indices += slot_indices;
+                }
+            }
+            if clipping.is_some_and(|(_, end_slot)| end_slot == (*slot).data) {
+                clipping = None;
+            }
+        }
+    }
+    vertices <= MAX_INDEXED_VERTICES && indices <= u16::MAX as usize
+}
+
+/// The renderables of the skeleton, batched by rusty_spine's combined drawer if it can handle the skeleton.
+///
+/// Otherwise the skeleton is drawn with one renderable per slot, so every slot ends up in its own draw.
+pub(crate) fn combined_renderables(
+    controller: &mut SkeletonController,
+) -> Vec<SkeletonCombinedRenderable> {
+    if fits_combined_drawer(&controller.skeleton) {
+        return controller.combined_renderables();
+    }
+    controller
+        .renderables()
+        .into_iter()
+        .map(|renderable| {
+            let vertex_count = renderable.vertices.len();
+            let color = renderable.color;
+            let dark_color = renderable.dark_color;
+            SkeletonCombinedRenderable {
+                vertices: renderable.vertices,
+                uvs: renderable.uvs,
+                indices: renderable.indices,
+                colors: vec![[color.r, color.g, color.b, color.a]; vertex_count],
+                dark_colors: vec![
+                    [dark_color.r, dark_color.g, dark_color.b, dark_color.a];
+                    vertex_count
+                ],
+                blend_mode: renderable.blend_mode,
+                premultiplied_alpha: renderable.premultiplied_alpha,
+                attachment_renderer_object: renderable.attachment_renderer_object,
+            }
+        })
+        .collect()
+}
@@ -1,75 +1,530 @@
 use std::{
+    cell::RefCell,
     collections::HashMap,
+    future::{poll_fn, Future},
     path::{Path, PathBuf},
-    sync::{Arc, Mutex, OnceLock},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, Once, OnceLock,
+    },
+    task::Poll,
 };
 
 use macroquad::{
-    logging::error,
-    miniquad::{MipmapFilterMode, RenderingBackend, TextureWrap},
+    logging::{error, warn},
+    miniquad::{MipmapFilterMode, TextureWrap},
     prelude::Color,
     texture::{FilterMode, Image, Texture2D},
     window::get_internal_gl,
 };
 use monad_quad::components::asyncs::AsyncState;
-use rusty_spine::atlas::{AtlasFilter, AtlasWrap};
+use rusty_spine::{
+    atlas::{AtlasFilter, AtlasWrap},
+    c::c_void,
+    Atlas,
+};
+
+use crate::SpineNodeError;
 
+/// Owns a texture cache and the queue of textures that are waiting to be deleted.
+///
+/// Every atlas is loaded through a runtime, and the textures of its pages are only shared
+/// with other atlases loaded through the same runtime.
+/// [setup_runtime] returns a global runtime that is used if there is no reason to have more than one.
 #[derive(Clone)]
-pub struct Token {
-    queue: Arc<Mutex<Vec<Texture2D>>>,
+pub struct SpineRuntime {
+    inner: Arc<RuntimeInner>,
+}
+
+/// The name [SpineRuntime] had before it owned its own texture cache.
+pub type Token = SpineRuntime;
+
+struct RuntimeInner {
+    cache: Mutex<HashMap<PathBuf, CachedTexture>>,
+    queue: Mutex<Vec<Texture2D>>,
+    texture_provider: Box<dyn TextureProvider>,
+    fallback_texture: FallbackTexture,
+}
+
+/// The atlas page a [TextureProvider] creates a texture for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TexturePage {
+    /// The path of the texture, which is the directory of the atlas joined with the page name.
+    pub path: PathBuf,
+    pub min_filter: AtlasFilter,
+    pub mag_filter: AtlasFilter,
+    pub u_wrap: AtlasWrap,
+    pub v_wrap: AtlasWrap,
+}
+
+/// What a [TextureProvider] returns for an atlas page.
+pub enum ProvidedTexture {
+    /// The texture is available right away.
+    Ready(Texture2D),
+    /// The texture could not be created, for the given reason.
+    Failed(String),
+    /// The texture is still loading. Skeletons skip the attachments using it until it is done.
+    Loading(Pin<Box<dyn Future<Output = Result<Texture2D, String>>>>),
 }
-impl Token {
-    pub(crate) fn delete_previous(&self, gl_context: &mut dyn RenderingBackend) {
-        for texture_delete in self.queue.lock().unwrap().drain(..) {
-            gl_context.delete_texture(texture_delete.raw_miniquad_id());
+
+impl From<Texture2D> for ProvidedTexture {
+    fn from(texture: Texture2D) -> Self {
+        Self::Ready(texture)
+    }
+}
+
+/// Creates the textures of atlas pages.
+///
+/// A texture is only requested once per path and [SpineRuntime], as long as some atlas page uses it.
+/// Atlas pages with the same path share it, including pages created while it is still loading,
+/// as do pages using a preloaded texture.
+pub trait TextureProvider: Send + Sync + 'static {
+    /// Creates or starts loading the texture for an atlas page.
+    fn load_texture(&self, page: &TexturePage) -> ProvidedTexture;
+    /// Sets the filters and wraps of a texture before an atlas page uses it.
+    ///
+    /// This is called for every atlas page, including pages that share a texture that was already loaded.
+    /// By default it applies the filters and wraps from the atlas, generating mipmaps if the min filter uses them.
+    fn prepare_texture(&self, texture: &Texture2D, page: &TexturePage) {
+        prepare_texture(
+            texture,
+            convert_filter(page.mag_filter).0,
+            convert_filter(page.min_filter),
+            convert_wrap(page.u_wrap),
+            convert_wrap(page.v_wrap),
+        );
+    }
+    /// Called when no atlas page uses the texture at `path` anymore, right before it gets queued for deletion.
+    fn dispose_texture(&self, path: &Path, texture: &Texture2D) {
+        let _ = (path, texture);
+    }
+}
+
+/// Loads textures through macroquad's file api.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultTextureProvider;
+
+impl TextureProvider for DefaultTextureProvider {
+    fn load_texture(&self, page: &TexturePage) -> ProvidedTexture {
+        let path = page.path.to_string_lossy().into_owned();
+        ProvidedTexture::Loading(Box::pin(async move {
+            macroquad::texture::load_texture(&path)
+                .await
+                .map_err(|e| e.to_string())
+        }))
+    }
+}
+
+/// What an atlas page uses when its texture fails to load.
+///
+/// The error is recorded either way, see [crate::SpineState::texture_errors].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FallbackTexture {
+    /// A 1x1 magenta texture, to make missing textures stand out.
+    #[default]
+    Magenta,
+    /// A 1x1 transparent texture, hiding the attachments of the page.
+    Transparent,
+    /// No texture at all. The attachments of the page are not rendered.
+    Fail,
+}
+
+impl FallbackTexture {
+    fn create(self) -> Option<Texture2D> {
+        let color = match self {
+            Self::Magenta => Color::new(1., 0., 1., 1.),
+            Self::Transparent => Color::new(0., 0., 0., 0.),
+            Self::Fail => return None,
+        };
+        Some(Texture2D::from_image(&Image::gen_image_color(1, 1, color)))
+    }
+}
+
+/// The options for [setup_runtime_with] and [SpineRuntime::with_options].
+pub struct SpineRuntimeOptions {
+    pub texture_provider: Box<dyn TextureProvider>,
+    pub fallback_texture: FallbackTexture,
+}
+
+impl Default for SpineRuntimeOptions {
+    fn default() -> Self {
+        Self {
+            texture_provider: Box::new(DefaultTextureProvider),
+            fallback_texture: FallbackTexture::default(),
         }
     }
 }
 
-static QUEUE: OnceLock<Token> = OnceLock::new();
-static CACHE: OnceLock<Arc<Mutex<HashMap<PathBuf, Texture2D>>>> = OnceLock::new();
+/// An atlas page whose texture failed to load.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextureError {
+    /// The name of the page as written in the atlas.
+    pub page: String,
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+/// A snapshot of the texture cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureCacheStats {
+    /// Textures that are loaded and cached.
+    pub textures: usize,
+    /// Textures that are used by an atlas page but are still loading.
+    pub loading: usize,
+    /// Textures that failed to load, including the ones that were replaced by a fallback texture.
+    pub failed: usize,
+    /// Textures in the cache that no atlas page uses, like preloaded textures that didn't end up being used.
+    pub unused: usize,
+    /// How many atlas pages use a texture from the cache, summed over all textures.
+    pub page_references: usize,
+    /// Textures that are no longer used and will be dropped the next time a skeleton renders.
+    pub queued_for_deletion: usize,
+}
+
+/// A texture together with how many atlas pages currently use it.
+#[derive(Default)]
+struct CachedTexture {
+    /// `None` while the texture is still loading, or if it failed to load without a fallback.
+    texture: Option<Texture2D>,
+    /// Whether `texture` is a [FallbackTexture] created by the runtime, so a later load may still replace it.
+    fallback: bool,
+    /// Whether an atlas page is loading the texture, which other pages using it wait for.
+    loading: bool,
+    pages: usize,
+    /// Why the texture failed to load.
+    error: Option<String>,
+    /// Changes every time `texture` does, so copies of it know when they are outdated.
+    generation: u64,
+}
+
+/// What [SpineRuntime::retain_texture] found in the cache.
+enum RetainedTexture {
+    Loaded(Texture2D),
+    /// Another atlas page is loading the texture.
+    Loading,
+    /// Nothing is loading the texture, so it is now up to the caller to.
+    Missing,
+}
+
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+fn next_generation() -> u64 {
+    NEXT_GENERATION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// The renderer object of every atlas page, and of every attachment using that page.
+pub(crate) struct PageTexture {
+    pub(crate) runtime: SpineRuntime,
+    pub(crate) path: PathBuf,
+    pub(crate) state: AsyncState<PathBuf>,
+}
+
+impl PageTexture {
+    /// # Safety
+    ///
+    /// `renderer_object` has to be a renderer object created by the callbacks of [setup_runtime].
+    pub(crate) unsafe fn from_renderer_object<'a>(renderer_object: *const c_void) -> &'a mut Self {
+        &mut *(renderer_object as *mut Self)
+    }
+}
+
+/// The pages of `atlas` whose texture failed to load, see [crate::SpineState::texture_errors].
+pub(crate) fn texture_errors(atlas: &Atlas) -> Vec<TextureError> {
+    atlas
+        .pages()
+        .filter_map(|page| {
+            let mut renderer_object = page.renderer_object();
+            let page_texture = unsafe { renderer_object.get::<PageTexture>() }?;
+            let reason = page_texture.runtime.texture_error(&page_texture.path)?;
+            Some(TextureError {
+                page: page.name().to_string(),
+                path: page_texture.path.clone(),
+                reason,
+            })
+        })
+        .collect()
+}
+
+static DEFAULT_RUNTIME: OnceLock<SpineRuntime> = OnceLock::new();
+/// Whether the rusty_spine callbacks are set up, either by this crate or by the user.
+static CALLBACKS: Once = Once::new();
+
+thread_local! {
+    /// The runtime that atlases are currently being loaded through, see [SpineRuntime::enter].
+    static CURRENT_RUNTIME: RefCell<Option<SpineRuntime>> = const { RefCell::new(None) };
+}
+
+impl SpineRuntime {
+    /// Creates a runtime with an empty texture cache, setting up the rusty_spine callbacks if needed.
+    pub fn new() -> Self {
+        Self::with_options(SpineRuntimeOptions::default())
+    }
+    /// Same as [SpineRuntime::new], but creates the textures through the given options.
+    ///
+    /// This doesn't create the global runtime, so [setup_runtime_with] can still configure that one.
+    pub fn with_options(options: SpineRuntimeOptions) -> Self {
+        install_callbacks();
+        Self::from_options(options)
+    }
+    fn from_options(options: SpineRuntimeOptions) -> Self {
+        Self {
+            inner: Arc::new(RuntimeInner {
+                cache: Mutex::default(),
+                queue: Mutex::default(),
+                texture_provider: options.texture_provider,
+                fallback_texture: options.fallback_texture,
+            }),
+        }
+    }
+    /// Runs `f` with this runtime as the one that newly created atlas pages get their textures from.
+    pub(crate) fn enter<R>(&self, f: impl FnOnce() -> R) -> R {
+        let previous = CURRENT_RUNTIME.with(|v| v.replace(Some(self.clone())));
+        let res = f();
+        CURRENT_RUNTIME.with(|v| v.replace(previous));
+        res
+    }
+    fn current() -> Self {
+        CURRENT_RUNTIME
+            .with(|v| v.borrow().clone())
+            .unwrap_or_else(|| unsafe { unchecked_assume_runtime_created() })
+    }
+    /// Whether both are the same runtime, sharing their texture cache.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+    /// Drops the textures that are no longer used by any atlas page.
+    ///
+    /// macroquad frees a texture once the last [Texture2D] pointing to it is dropped,
+    /// so textures that are still used elsewhere (like preloaded ones the user kept around) stay alive.
+    /// This needs to happen while macroquad is running, which is why disposed textures are queued.
+    ///
+    /// Every [crate::SpineState] calls this when it renders. Call it yourself after dropping skeletons
+    /// while nothing renders, like after unloading a level, to free their textures right away.
+    pub fn delete_unused_textures(&self) {
+        self.inner.queue.lock().unwrap().clear();
+    }
+    /// Takes a snapshot of the texture cache of this runtime.
+    pub fn cache_stats(&self) -> TextureCacheStats {
+        let cache = self.inner.cache.lock().unwrap();
+        TextureCacheStats {
+            textures: cache.values().filter(|v| v.texture.is_some()).count(),
+            loading: cache.values().filter(|v| v.loading).count(),
+            failed: cache.values().filter(|v| v.error.is_some()).count(),
+            unused: cache.values().filter(|v| v.pages == 0).count(),
+            page_references: cache.values().map(|v| v.pages).sum(),
+            queued_for_deletion: self.inner.queue.lock().unwrap().len(),
+        }
+    }
+    pub(crate) fn get_cache_item<V>(
+        &self,
+        a: &Path,
+        callback: impl FnOnce(Option<&Texture2D>) -> V,
+    ) -> V {
+        let cache = self.inner.cache.lock().unwrap();
+        let res = cache.get(a).and_then(|v| v.texture.as_ref());
+        callback(res)
+    }
+    /// Identifies the texture currently cached at `path`, see [CachedTexture::generation].
+    pub(crate) fn texture_generation(&self, path: &Path) -> Option<u64> {
+        let cache = self.inner.cache.lock().unwrap();
+        cache
+            .get(path)
+            .filter(|v| v.texture.is_some())
+            .map(|v| v.generation)
+    }
+    /// Why the texture at `path` failed to load, if it did.
+    pub(crate) fn fallback_texture(&self) -> FallbackTexture {
+        self.inner.fallback_texture
+    }
+    pub(crate) fn texture_error(&self, path: &Path) -> Option<String> {
+        let cache = self.inner.cache.lock().unwrap();
+        cache.get(path).and_then(|v| v.error.clone())
+    }
+    pub(crate) fn add_to_cache(&self, key: PathBuf, value: Texture2D) -> Option<Texture2D> {
+        let mut cache = self.inner.cache.lock().unwrap();
+        let cached = cache.entry(key).or_default();
+        cached.error = None;
+        cached.fallback = false;
+        cached.generation = next_generation();
+        cached.texture.replace(value)
+    }
+    /// Adds an atlas page as a user of the texture at `path`, returning the texture if it is already loaded.
+    ///
+    /// Textures that failed to load are tried again, keeping their fallback texture until that is done.
+    fn retain_texture(&self, path: &Path) -> RetainedTexture {
+        let mut cache = self.inner.cache.lock().unwrap();
+        let cached = cache.entry(path.to_owned()).or_default();
+        cached.pages += 1;
+        if let Some(texture) = cached.texture.as_ref().filter(|_| !cached.fallback) {
+            RetainedTexture::Loaded(texture.clone())
+        } else if cached.loading {
+            RetainedTexture::Loading
+        } else {
+            cached.loading = true;
+            RetainedTexture::Missing
+        }
+    }
+    /// Waits for another atlas page to finish loading the texture at `path`,
+    /// returning `None` if it failed to load without a fallback.
+    fn wait_for_texture(&self, path: PathBuf) -> impl Future<Output = Option<Texture2D>> {
+        let runtime = self.clone();
+        poll_fn(move |cx| {
+            let cache = runtime.inner.cache.lock().unwrap();
+            match cache.get(&path) {
+                Some(cached) if cached.loading => {
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                }
+                cached => Poll::Ready(cached.and_then(|v| v.texture.clone())),
+            }
+        })
+    }
+    /// Stores a texture that finished loading, or the fallback texture and the error if it failed to.
+    ///
+    /// If another atlas page loaded the same texture in the meantime, that one is kept and returned instead,
+    /// unless it is a fallback texture.
+    fn store_loaded_texture(
+        &self,
+        path: &Path,
+        loaded: Result<Texture2D, String>,
+    ) -> Option<Texture2D> {
+        let (mut texture, error, fallback) = match loaded {
+            Ok(texture) => (Some(texture), None, false),
+            Err(reason) => {
+                error!(
+                    "Could not load texture at {}.\nError: {reason}",
+                    path.display()
+                );
+                (self.inner.fallback_texture.create(), Some(reason), true)
+            }
+        };
+        let mut cache = self.inner.cache.lock().unwrap();
+        let unused = match cache.get_mut(path) {
+            Some(cached) => {
+                cached.loading = false;
+                match &cached.texture {
+                    Some(existing) if !cached.fallback => {
+                        texture.replace(existing.clone()).map(|v| (v, fallback))
+                    }
+                    _ => {
+                        let replaced = std::mem::replace(&mut cached.texture, texture.clone());
+                        let replaced = replaced.map(|v| (v, cached.fallback));
+                        cached.fallback = fallback;
+                        cached.error = error;
+                        cached.generation = next_generation();
+                        replaced
+                    }
+                }
+            }
+            // Every page using it got disposed while it was loading
+            None => texture.clone().map(|v| (v, fallback)),
+        };
+        drop(cache);
+        if let Some((unused, fallback)) = unused {
+            self.dispose_texture(path, unused, fallback);
+        }
+        texture
+    }
+    /// Stores the result of [TextureProvider::load_texture] and prepares the texture for the page.
+    fn finish_loading(&self, page: &TexturePage, loaded: Result<Texture2D, String>) {
+        if let Some(texture) = self.store_loaded_texture(&page.path, loaded) {
+            self.inner.texture_provider.prepare_texture(&texture, page);
+        }
+    }
+    /// Removes an atlas page as a user of the texture at `path`,
+    /// queueing the texture for deletion if it was the last one.
+    fn release_texture(&self, path: &Path) {
+        let mut cache = self.inner.cache.lock().unwrap();
+        let Some(cached) = cache.get_mut(path) else {
+            return;
+        };
+        cached.pages = cached.pages.saturating_sub(1);
+        if cached.pages == 0 {
+            let cached = cache.remove(path);
+            drop(cache);
+            if let Some(CachedTexture {
+                texture: Some(texture),
+                fallback,
+                ..
+            }) = cached
+            {
+                self.dispose_texture(path, texture, fallback);
+            }
+        }
+    }
+    /// Lets the [TextureProvider] know the texture is no longer used and queues it for deletion.
+    /// Fallback textures were never provided by it, so it isn't told about those.
+    ///
+    /// The cache must not be locked, so the provider is free to use this runtime.
+    fn dispose_texture(&self, path: &Path, texture: Texture2D, fallback: bool) {
+        if !fallback {
+            self.inner.texture_provider.dispose_texture(path, &texture);
+        }
+        self.inner.queue.lock().unwrap().push(texture);
+    }
+}
 
-pub fn get_cache_item<V>(a: &Path, callback: impl FnOnce(Option<&Texture2D>) -> V) -> V {
-    let cache = CACHE.get_or_init(Default::default).lock().unwrap();
-    let res = cache.get(a);
-    callback(res)
+impl Default for SpineRuntime {
+    fn default() -> Self {
+        Self::new()
+    }
 }
-pub fn get_cache_mut<V>(callback: impl FnOnce(&mut HashMap<PathBuf, Texture2D>) -> V) -> V {
-    let mut cache = CACHE.get_or_init(Default::default).lock().unwrap();
-    callback(&mut cache)
+
+/// Splits an atlas filter into the filter used within a mipmap level and the one used between levels.
+///
+/// The names follow libGDX, where `MipMapNearestLinear` means `GL_NEAREST_MIPMAP_LINEAR`
+/// and `MipMap` is the same as `MipMapLinearLinear`.
+fn convert_filter(filter: AtlasFilter) -> (FilterMode, MipmapFilterMode) {
+    match filter {
+        AtlasFilter::Linear => (FilterMode::Linear, MipmapFilterMode::None),
+        AtlasFilter::Nearest => (FilterMode::Nearest, MipmapFilterMode::None),
+        AtlasFilter::Mipmap | AtlasFilter::MipmapLinearLinear => {
+            (FilterMode::Linear, MipmapFilterMode::Linear)
+        }
+        AtlasFilter::MipmapNearestNearest => (FilterMode::Nearest, MipmapFilterMode::Nearest),
+        AtlasFilter::MipmapLinearNearest => (FilterMode::Linear, MipmapFilterMode::Nearest),
+        AtlasFilter::MipmapNearestLinear => (FilterMode::Nearest, MipmapFilterMode::Linear),
+        AtlasFilter::UnknownFilter => {
+            warn!("Unsupported texture filter mode: {filter:?}");
+            (FilterMode::Linear, MipmapFilterMode::None)
+        }
+    }
 }
-pub fn add_to_cache(key: PathBuf, value: Texture2D) -> Option<Texture2D> {
-    let mut cache = CACHE.get_or_init(Default::default).lock().unwrap();
-    cache.insert(key, value)
+
+fn convert_wrap(wrap: AtlasWrap) -> TextureWrap {
+    match wrap {
+        AtlasWrap::ClampToEdge => TextureWrap::Clamp,
+        AtlasWrap::MirroredRepeat => TextureWrap::Mirror,
+        AtlasWrap::Repeat => TextureWrap::Repeat,
+        wrap => {
+            warn!("Unsupported texture wrap mode: {wrap:?}");
+            TextureWrap::Clamp
+        }
+    }
 }
 
 fn prepare_texture(
-    texture: Texture2D,
+    texture: &Texture2D,
     mag_filter: FilterMode,
-    min_filter: FilterMode,
+    (min_filter, mipmap_filter): (FilterMode, MipmapFilterMode),
     x_wrap: TextureWrap,
     y_wrap: TextureWrap,
 ) {
     let ctx = unsafe { get_internal_gl() };
     let texture = texture.raw_miniquad_id();
-    ctx.quad_context
-        .texture_set_filter(texture, FilterMode::Linear, MipmapFilterMode::None);
+    // Sampling with a mipmap filter without mipmaps would leave the texture incomplete
+    if mipmap_filter != MipmapFilterMode::None {
+        ctx.quad_context.texture_generate_mipmaps(texture);
+    }
+    // Magnification never uses mipmaps
     ctx.quad_context.texture_set_mag_filter(texture, mag_filter);
     ctx.quad_context
-        .texture_set_min_filter(texture, min_filter, MipmapFilterMode::None);
+        .texture_set_min_filter(texture, min_filter, mipmap_filter);
     ctx.quad_context.texture_set_wrap(texture, x_wrap, y_wrap);
 }
 
-pub async fn load_texture_or_fallback(path: &str) -> Texture2D {
-    macroquad::texture::load_texture(path)
-        .await
-        .unwrap_or_else(|e| {
-            error!("Could not load texture at {}.\nError: {e}", path);
-            Texture2D::from_image(&Image::gen_image_color(1, 1, Color::new(1., 0., 1., 1.)))
-        })
-}
-
-/// Creates a [crate::Token] without checking if the callbacks for rusty_spine are setup correctly.
+/// Returns the global [SpineRuntime] without checking if the callbacks for rusty_spine are setup correctly.
 ///
 /// # Safety
 ///
@@ -87,71 +542,260 @@ pub async fn load_texture_or_fallback(path: &str) -> Texture2D {
 /// but that isn't a guarantee nor does it mean that [rusty_spine]
 /// doesn't run into UB or similar.
 ///
-pub unsafe fn unchecked_assume_runtime_created() -> Token {
-    QUEUE
-        .get_or_init(|| Token {
-            queue: Arc::new(Mutex::new(Vec::new())),
-        })
+pub unsafe fn unchecked_assume_runtime_created() -> SpineRuntime {
+    // Keep the callbacks that were set up instead of replacing them later on
+    CALLBACKS.call_once(|| {});
+    DEFAULT_RUNTIME
+        .get_or_init(|| SpineRuntime::from_options(SpineRuntimeOptions::default()))
         .to_owned()
 }
 
-fn setup_spine() -> Token {
+fn setup_spine() -> SpineRuntime {
+    install_callbacks();
+    unsafe { unchecked_assume_runtime_created() }
+}
+
+fn install_callbacks() {
+    CALLBACKS.call_once(set_callbacks);
+}
+
+fn set_callbacks() {
     rusty_spine::extension::set_create_texture_cb(|atlas_page, path| {
-        fn convert_filter(filter: AtlasFilter) -> FilterMode {
-            match filter {
-                AtlasFilter::Linear => FilterMode::Linear,
-                AtlasFilter::Nearest => FilterMode::Nearest,
-                filter => {
-                    println!("Unsupported texture filter mode: {filter:?}");
-                    FilterMode::Linear
-                }
+        let page = TexturePage {
+            path: PathBuf::from(path),
+            min_filter: atlas_page.min_filter(),
+            mag_filter: atlas_page.mag_filter(),
+            u_wrap: atlas_page.u_wrap(),
+            v_wrap: atlas_page.v_wrap(),
+        };
+        let runtime = SpineRuntime::current();
+        let provider = &runtime.inner.texture_provider;
+        let state = match runtime.retain_texture(&page.path) {
+            RetainedTexture::Loaded(texture) => {
+                provider.prepare_texture(&texture, &page);
+                AsyncState::new_done(page.path.clone())
             }
-        }
-        fn convert_wrap(wrap: AtlasWrap) -> TextureWrap {
-            match wrap {
-                AtlasWrap::ClampToEdge => TextureWrap::Clamp,
-                AtlasWrap::MirroredRepeat => TextureWrap::Mirror,
-                AtlasWrap::Repeat => TextureWrap::Repeat,
-                wrap => {
-                    println!("Unsupported texture wrap mode: {wrap:?}");
-                    TextureWrap::Clamp
-                }
+            RetainedTexture::Loading => {
+                let runtime = runtime.clone();
+                let page = page.clone();
+                AsyncState::new_loading(async move {
+                    if let Some(texture) = runtime.wait_for_texture(page.path.clone()).await {
+                        runtime
+                            .inner
+                            .texture_provider
+                            .prepare_texture(&texture, &page);
+                    }
+                    page.path
+                })
             }
-        }
-        let min_filter = convert_filter(atlas_page.min_filter());
-        let mag_filter = convert_filter(atlas_page.mag_filter());
-        let x_wrap = convert_wrap(atlas_page.u_wrap());
-        let y_wrap = convert_wrap(atlas_page.v_wrap());
-        let path = path.to_owned();
-        let mut full_path = PathBuf::new();
-        full_path.push(&path);
-        let value = if get_cache_mut(|v| v.contains_key(&full_path)) {
-            let texture = get_cache_item(&full_path, |v| v.cloned().unwrap());
-            prepare_texture(texture, mag_filter, min_filter, x_wrap, y_wrap);
-            AsyncState::new_done(full_path)
-        } else {
-            AsyncState::new_loading(async move {
-                let texture = load_texture_or_fallback(&path).await;
-                add_to_cache(full_path.clone(), texture.clone());
-                prepare_texture(texture, mag_filter, min_filter, x_wrap, y_wrap);
-                full_path
-            })
+            RetainedTexture::Missing => match provider.load_texture(&page) {
+                ProvidedTexture::Ready(texture) => {
+                    runtime.finish_loading(&page, Ok(texture));
+                    AsyncState::new_done(page.path.clone())
+                }
+                ProvidedTexture::Failed(reason) => {
+                    runtime.finish_loading(&page, Err(reason));
+                    AsyncState::new_done(page.path.clone())
+                }
+                ProvidedTexture::Loading(texture) => {
+                    let runtime = runtime.clone();
+                    let page = page.clone();
+                    AsyncState::new_loading(async move {
+                        runtime.finish_loading(&page, texture.await);
+                        page.path
+                    })
+                }
+            },
         };
-        atlas_page.renderer_object().set(value)
+        atlas_page.renderer_object().set(PageTexture {
+            runtime,
+            path: page.path,
+            state,
+        })
     });
-    let token = unsafe { unchecked_assume_runtime_created() };
-    //let texture_delete_queue_cb = token.queue.clone();
-    rusty_spine::extension::set_dispose_texture_cb(move |atlas_page| unsafe {
-        atlas_page
-            .renderer_object()
-            .dispose::<AsyncState<PathBuf>>()
+    rusty_spine::extension::set_dispose_texture_cb(|atlas_page| unsafe {
+        let mut renderer_object = atlas_page.renderer_object();
+        if let Some(page) = renderer_object.get::<PageTexture>() {
+            page.runtime.release_texture(&page.path);
+        }
+        renderer_object.dispose::<PageTexture>();
     });
-    token
 }
 
-pub fn setup_runtime() -> Token {
-    QUEUE
+/// Sets up the callbacks rusty_spine needs and returns the global [SpineRuntime].
+pub fn setup_runtime() -> SpineRuntime {
+    DEFAULT_RUNTIME
         .get()
         .map(ToOwned::to_owned)
         .unwrap_or_else(setup_spine)
 }
+
+/// Same as [setup_runtime], but creates the textures of the global [SpineRuntime] through the given options.
+///
+/// Returns [SpineNodeError::RuntimeAlreadyCreated] if the global runtime already exists,
+/// as its options can't be changed after the fact. Use [SpineRuntime::with_options] for that instead.
+pub fn setup_runtime_with(options: SpineRuntimeOptions) -> Result<SpineRuntime, SpineNodeError> {
+    DEFAULT_RUNTIME
+        .set(SpineRuntime::from_options(options))
+        .map_err(|_| SpineNodeError::RuntimeAlreadyCreated)?;
+    Ok(setup_spine())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::Future,
+        path::Path,
+        pin::pin,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        task::{Context, Poll, Waker},
+    };
+
+    use macroquad::{
+        miniquad::{MipmapFilterMode, RawId, TextureId},
+        texture::{FilterMode, Texture2D},
+    };
+    use rusty_spine::atlas::AtlasFilter;
+
+    use super::{
+        convert_filter, CachedTexture, ProvidedTexture, RetainedTexture, SpineRuntime,
+        SpineRuntimeOptions, TexturePage, TextureProvider,
+    };
+
+    /// A texture that isn't managed by macroquad, so it works without a GL context.
+    fn texture(id: u32) -> Texture2D {
+        Texture2D::from_miniquad_texture(TextureId::from_raw_id(RawId::OpenGl(id)))
+    }
+
+    fn runtime() -> SpineRuntime {
+        SpineRuntime::from_options(SpineRuntimeOptions::default())
+    }
+
+    /// Counts how many textures it was told to dispose.
+    struct CountingProvider(Arc<AtomicUsize>);
+
+    impl TextureProvider for CountingProvider {
+        fn load_texture(&self, _page: &TexturePage) -> ProvidedTexture {
+            ProvidedTexture::Failed("not needed".to_string())
+        }
+        fn dispose_texture(&self, _path: &Path, _texture: &Texture2D) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn runtimes_have_their_own_cache() {
+        let (first, second) = (runtime(), runtime());
+        let path = Path::new("page.png");
+        first.add_to_cache(path.to_owned(), texture(1));
+        assert_eq!(first.get_cache_item(path, |v| v.cloned()), Some(texture(1)));
+        assert_eq!(second.get_cache_item(path, |v| v.cloned()), None);
+        assert_eq!(first.cache_stats().textures, 1);
+        assert_eq!(second.cache_stats().textures, 0);
+        assert!(first.ptr_eq(&first.clone()));
+        assert!(!first.ptr_eq(&second));
+    }
+
+    #[test]
+    fn released_textures_are_queued_until_deleted() {
+        let (first, second) = (runtime(), runtime());
+        let path = Path::new("page.png");
+        first.add_to_cache(path.to_owned(), texture(1));
+        for _ in 0..2 {
+            assert!(matches!(
+                first.retain_texture(path),
+                RetainedTexture::Loaded(v) if v == texture(1)
+            ));
+        }
+        assert_eq!(first.cache_stats().page_references, 2);
+
+        first.release_texture(path);
+        assert_eq!(first.cache_stats().queued_for_deletion, 0);
+        first.release_texture(path);
+        assert_eq!(first.cache_stats().textures, 0);
+        assert_eq!(first.cache_stats().queued_for_deletion, 1);
+
+        second.delete_unused_textures();
+        assert_eq!(first.cache_stats().queued_for_deletion, 1);
+        first.delete_unused_textures();
+        assert_eq!(first.cache_stats().queued_for_deletion, 0);
+    }
+
+    #[test]
+    fn pages_wait_for_a_texture_that_is_loading() {
+        let runtime = runtime();
+        let path = Path::new("page.png");
+        assert!(matches!(
+            runtime.retain_texture(path),
+            RetainedTexture::Missing
+        ));
+        assert!(matches!(
+            runtime.retain_texture(path),
+            RetainedTexture::Loading
+        ));
+        assert_eq!(runtime.cache_stats().loading, 1);
+
+        let mut waiting = pin!(runtime.wait_for_texture(path.to_owned()));
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(waiting.as_mut().poll(&mut cx).is_pending());
+        runtime.store_loaded_texture(path, Ok(texture(1)));
+        assert_eq!(waiting.poll(&mut cx), Poll::Ready(Some(texture(1))));
+        assert_eq!(runtime.cache_stats().loading, 0);
+        assert_eq!(runtime.cache_stats().page_references, 2);
+    }
+
+    #[test]
+    fn loaded_textures_replace_fallback_textures() {
+        let disposed = Arc::new(AtomicUsize::new(0));
+        let runtime = SpineRuntime::from_options(SpineRuntimeOptions {
+            texture_provider: Box::new(CountingProvider(disposed.clone())),
+            ..Default::default()
+        });
+        let path = Path::new("page.png");
+        runtime.inner.cache.lock().unwrap().insert(
+            path.to_owned(),
+            CachedTexture {
+                texture: Some(texture(1)),
+                fallback: true,
+                pages: 1,
+                error: Some("missing".to_string()),
+                ..Default::default()
+            },
+        );
+        assert!(matches!(
+            runtime.retain_texture(path),
+            RetainedTexture::Missing
+        ));
+
+        assert_eq!(
+            runtime.store_loaded_texture(path, Ok(texture(2))),
+            Some(texture(2))
+        );
+        assert_eq!(
+            runtime.get_cache_item(path, |v| v.cloned()),
+            Some(texture(2))
+        );
+        assert_eq!(runtime.texture_error(path), None);
+        assert_eq!(runtime.cache_stats().queued_for_deletion, 1);
+        assert_eq!(disposed.load(Ordering::Relaxed), 0);
+
+        runtime.release_texture(path);
+        runtime.release_texture(path);
+        assert_eq!(disposed.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn mipmap_filters_follow_libgdx_naming() {
+        assert_eq!(
+            convert_filter(AtlasFilter::MipmapLinearNearest),
+            (FilterMode::Linear, MipmapFilterMode::Nearest)
+        );
+        assert_eq!(
+            convert_filter(AtlasFilter::MipmapNearestLinear),
+            (FilterMode::Nearest, MipmapFilterMode::Linear)
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use rusty_spine::{c::spSkin_getAttachments, SkeletonData};
+
+use crate::c_helpers::{c_array, c_string};
+
+/// A read only snapshot of everything a skeleton contains.
+///
+/// It can be created from a [crate::SpineState], a [crate::SpineAsset]
+/// or directly from [SkeletonData], which doesn't need a GL context.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkeletonInfo {
+    /// The version of Spine that exported the skeleton.
+    pub version: String,
+    pub hash: String,
+    /// The x position of the skeleton's bounds in the setup pose, as stored in the skeleton file.
+    pub x: f32,
+    /// The y position of the skeleton's bounds in the setup pose, as stored in the skeleton file.
+    pub y: f32,
+    /// The width of the skeleton in the setup pose, as stored in the skeleton file.
+    pub width: f32,
+    /// The height of the skeleton in the setup pose, as stored in the skeleton file.
+    pub height: f32,
+    pub animations: Vec<AnimationInfo>,
+    pub skins: Vec<String>,
+    /// Every bone, ordered so that parents come before their children.
+    pub bones: Vec<BoneInfo>,
+    pub slots: Vec<SlotInfo>,
+    pub events: Vec<EventInfo>,
+    pub ik_constraints: Vec<String>,
+    pub transform_constraints: Vec<String>,
+    pub path_constraints: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationInfo {
+    pub name: String,
+    /// Duration in seconds.
+    pub duration: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoneInfo {
+    pub name: String,
+    /// `None` for the root bone.
+    pub parent: Option<String>,
+    pub length: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlotInfo {
+    pub name: String,
+    pub bone: String,
+    /// The attachment that is visible in the setup pose.
+    pub setup_attachment: Option<String>,
+    /// Every attachment any of the skins has for this slot.
+    pub attachments: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventInfo {
+    pub name: String,
+    pub int: i32,
+    pub float: f32,
+    pub string: String,
+    pub audio_path: String,
+    pub volume: f32,
+    pub balance: f32,
+}
+
+impl From<&SkeletonData> for SkeletonInfo {
+    fn from(skeleton_data: &SkeletonData) -> Self {
+        let mut slots: Vec<_> = skeleton_data
+            .slots()
+            .map(|slot| SlotInfo {
+                name: slot.name().to_string(),
+                bone: slot.bone_data().name().to_string(),
+                setup_attachment: Some(slot.attachment_name())
+                    .filter(|v| !v.is_empty())
+                    .map(ToOwned::to_owned),
+                attachments: Vec::new(),
+            })
+            .collect();
+        for skin in skeleton_data.skins() {
+            // Skin::attachments doesn't expose the name the attachment is stored under,
+            // which is the one needed to set attachments by name.
+            let mut entry = unsafe { spSkin_getAttachments(skin.c_ptr()) };
+            while !entry.is_null() {
+                let (slot_index, name) = unsafe { ((*entry).slotIndex, c_string((*entry).name)) };
+                entry = unsafe { (*entry).next };
+                let Some(slot) = slots.get_mut(slot_index as usize) else {
+                    continue;
+                };
+                if !slot.attachments.contains(&name) {
+                    slot.attachments.push(name);
+                }
+            }
+        }
+        let c_skeleton_data = unsafe { &*skeleton_data.c_ptr() };
+        let (events, ik_constraints, transform_constraints, path_constraints) = unsafe {
+            (
+                c_array(c_skeleton_data.events, c_skeleton_data.eventsCount)
+                    .iter()
+                    .map(|&event| {
+                        let event = &*event;
+                        EventInfo {
+                            name: c_string(event.name),
+                            int: event.intValue,
+                            float: event.floatValue,
+                            string: c_string(event.stringValue),
+                            audio_path: c_string(event.audioPath),
+                            volume: event.volume,
+                            balance: event.balance,
+                        }
+                    })
+                    .collect(),
+                c_array(
+                    c_skeleton_data.ikConstraints,
+                    c_skeleton_data.ikConstraintsCount,
+                )
+                .iter()
+                .map(|&v| c_string((*v).name))
+                .collect(),
+                c_array(
+                    c_skeleton_data.transformConstraints,
+                    c_skeleton_data.transformConstraintsCount,
+                )
+                .iter()
+                .map(|&v| c_string((*v).name))
+                .collect(),
+                c_array(
+                    c_skeleton_data.pathConstraints,
+                    c_skeleton_data.pathConstraintsCount,
+                )
+                .iter()
+                .map(|&v| c_string((*v).name))
+                .collect(),
+            )
+        };
+        Self {
+            version: skeleton_data.version().to_string(),
+            hash: skeleton_data.hash().to_string(),
+            x: skeleton_data.x(),
+            y: skeleton_data.y(),
+            width: skeleton_data.width(),
+            height: skeleton_data.height(),
+            animations: skeleton_data
+                .animations()
+                .map(|animation| AnimationInfo {
+                    name: animation.name().to_string(),
+                    duration: animation.duration(),
+                })
+                .collect(),
+            skins: skeleton_data
+                .skins()
+                .map(|skin| skin.name().to_string())
+                .collect(),
+            bones: skeleton_data
+                .bones()
+                .map(|bone| BoneInfo {
+                    name: bone.name().to_string(),
+                    parent: bone.parent().map(|v| v.name().to_string()),
+                    length: bone.length(),
+                })
+                .collect(),
+            slots,
+            events,
+            ik_constraints,
+            transform_constraints,
+            path_constraints,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rusty_spine::{Atlas, SkeletonJson};
+
+    use super::SkeletonInfo;
+
+    /// Loads the example skeleton without a GL context or texture callbacks.
+    fn example_info() -> SkeletonInfo {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/asset_exports");
+        let atlas = Arc::new(Atlas::new_from_file(format!("{dir}/skeleton.atlas")).unwrap());
+        let skeleton_data = SkeletonJson::new(atlas)
+            .read_skeleton_data_file(format!("{dir}/skeleton.json"))
+            .unwrap();
+        SkeletonInfo::from(&skeleton_data)
+    }
+
+    #[test]
+    fn reads_example_skeleton() {
+        let info = example_info();
+        assert_eq!(info.version, "4.1.23");
+        assert_eq!(info.hash, "QagZmJSLswc");
+        let animations: Vec<_> = info.animations.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(animations, ["animation", "wag_tail"]);
+        assert_eq!(info.skins, ["default", "no_mask", "with_mask"]);
+        assert_eq!(info.bones.len(), 36);
+        assert_eq!(info.slots.len(), 9);
+        assert_eq!(info.ik_constraints.len(), 4);
+        assert!(info.events.is_empty());
+    }
+
+    #[test]
+    fn parents_come_before_children() {
+        let info = example_info();
+        assert_eq!(info.bones[0].name, "root");
+        assert_eq!(info.bones[0].parent, None);
+        for (index, bone) in info.bones.iter().enumerate().skip(1) {
+            let parent = bone.parent.as_deref().unwrap();
+            assert!(info.bones[..index].iter().any(|v| v.name == parent));
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use macroquad::color::Color;
+use rusty_spine::{
+    c::{spSkeleton, spSkin_getAttachments, spSlot},
+    Slot,
+};
+
+use crate::{c_helpers::c_string, SpineNodeError, SpineState};
+
+pub(crate) fn to_spine_color(color: Color) -> rusty_spine::Color {
+    rusty_spine::Color::new_rgba(color.r, color.g, color.b, color.a)
+}
+pub(crate) fn from_spine_color(color: rusty_spine::Color) -> Color {
+    Color::new(color.r, color.g, color.b, color.a)
+}
+
+/// The name the attachment of the slot is stored under in the skin, which is what
+/// [SpineState::set_slot_attachment] expects. It can differ from the name of the attachment itself.
+///
+/// The skin of the skeleton is searched before the default skin, like setting an attachment does.
+/// If neither has it, the name of the attachment is used instead.
+///
+/// # Safety
+///
+/// `slot` has to be a slot of `skeleton`.
+pub(crate) unsafe fn attachment_key(
+    skeleton: *const spSkeleton,
+    slot: *const spSlot,
+) -> Option<String> {
+    let attachment = (*slot).attachment;
+    if attachment.is_null() {
+        return None;
+    }
+    let slot_index = (*(*slot).data).index;
+    for skin in [(*skeleton).skin, (*(*skeleton).data).defaultSkin] {
+        if skin.is_null() {
+            continue;
+        }
+        // Skin::attachments doesn't expose the name the attachment is stored under
+        let mut entry = spSkin_getAttachments(skin);
+        while !entry.is_null() {
+            if (*entry).slotIndex == slot_index && (*entry).attachment == attachment {
+                return Some(c_string((*entry).name));
+            }
+            entry = (*entry).next;
+        }
+    }
+    Some(c_string((*attachment).name))
+}
+
+impl SpineState {
+    fn with_slot<T>(
+        &self,
+        name: &str,
+        cb: impl FnOnce(&mut Slot) -> T,
+    ) -> Result<T, SpineNodeError> {
+        let spine = self.spine.borrow();
+        let mut controller = spine.controller.borrow_mut();
+        let mut slot =
+            controller
+                .skeleton
+                .find_slot_mut(name)
+                .ok_or_else(|| SpineNodeError::UnknownSlot {
+                    name: name.to_string(),
+                })?;
+        Ok(cb(&mut slot))
+    }
+    /// Shows the attachment with the given name in the slot, or hides the slot if `attachment` is `None`.
+    ///
+    /// Animations that key the attachment of this slot will overwrite this.
+    pub fn set_slot_attachment(
+        &mut self,
+        slot: &str,
+        attachment: Option<&str>,
+    ) -> Result<(), SpineNodeError> {
+        self.with_slot(slot, |_| ())?;
+        let spine = self.spine.borrow();
+        let mut controller = spine.controller.borrow_mut();
+        if controller.skeleton.set_attachment(slot, attachment) {
+            Ok(())
+        } else {
+            Err(SpineNodeError::UnknownAttachment {
+                slot: slot.to_string(),
+                name: attachment.unwrap_or_default().to_string(),
+            })
+        }
+    }
+    /// Hides the slot by removing its attachment.
+    pub fn hide_slot(&mut self, slot: &str) -> Result<(), SpineNodeError> {
+        self.set_slot_attachment(slot, None)
+    }
+    /// The name of the attachment that is currently shown in the slot,
+    /// as stored in the skin so it can be passed to [SpineState::set_slot_attachment].
+    pub fn slot_attachment(&self, slot: &str) -> Result<Option<String>, SpineNodeError> {
+        let spine = self.spine.borrow();
+        let controller = spine.controller.borrow();
+        let slot =
+            controller
+                .skeleton
+                .find_slot(slot)
+                .ok_or_else(|| SpineNodeError::UnknownSlot {
+                    name: slot.to_string(),
+                })?;
+        Ok(unsafe { attachment_key(controller.skeleton.c_ptr(), slot.c_ptr()) })
+    }
+    pub fn slot_color(&self, slot: &str) -> Result<Color, SpineNodeError> {
+        self.with_slot(slot, |slot| from_spine_color(slot.color()))
+    }
+    /// Sets the color that the texture of the slot gets multiplied with.
+    ///
+    /// Animations that key the color of this slot will overwrite this.
+    pub fn set_slot_color(&mut self, slot: &str, color: Color) -> Result<(), SpineNodeError> {
+        self.with_slot(slot, |slot| *slot.color_mut() = to_spine_color(color))
+    }
+    /// The dark color used for two color tinting, or `None` if two color tinting is disabled for the slot.
+    pub fn slot_dark_color(&self, slot: &str) -> Result<Option<Color>, SpineNodeError> {
+        self.with_slot(slot, |slot| slot.dark_color().map(from_spine_color))
+    }
+    /// Sets the dark color used for two color tinting.
+    ///
+    /// Two color tinting has to be enabled for the slot in Spine.
+    pub fn set_slot_dark_color(&mut self, slot: &str, color: Color) -> Result<(), SpineNodeError> {
+        let slot_name = slot;
+        self.with_slot(slot, |slot| {
+            let dark_color = unsafe { (*slot.c_ptr()).darkColor };
+            if dark_color.is_null() {
+                return Err(SpineNodeError::NoDarkColor {
+                    slot: slot_name.to_string(),
+                });
+            }
+            let color = to_spine_color(color);
+            unsafe {
+                (*dark_color).r = color.r;
+                (*dark_color).g = color.g;
+                (*dark_color).b = color.b;
+                (*dark_color).a = color.a;
+            }
+            Ok(())
+        })?
+    }
+    /// Resets the attachment and colors of the slot to the setup pose.
+    pub fn reset_slot(&mut self, slot: &str) -> Result<(), SpineNodeError> {
+        self.with_slot(slot, |slot| slot.set_to_setup_pose())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rusty_spine::{Atlas, Skeleton, SkeletonJson};
+
+    use super::attachment_key;
+
+    #[test]
+    fn attachments_are_named_by_their_skin_key() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/asset_exports");
+        let atlas = Arc::new(Atlas::new_from_file(format!("{dir}/skeleton.atlas")).unwrap());
+        let skeleton_data = SkeletonJson::new(atlas)
+            .read_skeleton_data_file(format!("{dir}/skeleton.json"))
+            .unwrap();
+        let mut skeleton = Skeleton::new(Arc::new(skeleton_data));
+        skeleton.set_skin_by_name("with_mask").unwrap();
+        // The skin stores the attachment named "mask" as "mask2"
+        assert!(skeleton.set_attachment("mask", Some("mask2")));
+        let slot = skeleton.find_slot("mask").unwrap();
+        assert_eq!(slot.attachment().unwrap().name(), "mask");
+        let key = unsafe { attachment_key(skeleton.c_ptr(), slot.c_ptr()) };
+        assert_eq!(key.as_deref(), Some("mask2"));
+
+        assert!(skeleton.set_attachment("mask", None));
+        let slot = skeleton.find_slot("mask").unwrap();
+        assert_eq!(
+            unsafe { attachment_key(skeleton.c_ptr(), slot.c_ptr()) },
+            None
+        );
+    }
+}
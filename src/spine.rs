@@ -1,4 +1,4 @@
-use std::{borrow::Cow, cell::RefCell, path::PathBuf, rc::Rc, sync::Arc};
+use std::{borrow::Cow, cell::RefCell, collections::HashMap, rc::Rc, sync::Arc};
 
 use macroquad::{
     miniquad::CullFace,
@@ -8,10 +8,13 @@ use macroquad::{
 use rusty_spine::{
     controller::{SkeletonController, SkeletonControllerSettings},
     draw::{ColorSpace, CullDirection},
-    AnimationStateData, Atlas, SkeletonBinary, SkeletonData, SkeletonJson, SpineError,
+    Atlas,
 };
 
-use crate::{setup_rusty_spine::add_to_cache, SpineSkeletonBytes, SpineSkeletonPath, Token};
+use crate::{
+    constraints::DisabledConstraint, tint::TintTween, BoneOverride, SpineAsset, SpineNodeError,
+    SpineRuntime, SpineSkeletonBytes, SpineSkeletonPath,
+};
 
 #[derive(Clone)]
 pub struct AnimationOptions {
@@ -19,14 +22,20 @@ pub struct AnimationOptions {
     pub animation_name: String,
 }
 
-struct SpineCreationOptions {
-    position: Vec2,
-    scale: f32,
-    skin: Option<String>,
-    backface_culling: bool,
-    animation: Option<AnimationOptions>,
-    skeleton_data: SkeletonData,
-    atlas: Arc<Atlas>,
+/// How long it takes to crossfade from one animation to another.
+#[derive(Clone)]
+pub struct AnimationMix {
+    pub from: String,
+    pub to: String,
+    pub duration: f32,
+}
+
+pub(crate) struct SpineCreationOptions<'a> {
+    pub(crate) position: Vec2,
+    pub(crate) scale: f32,
+    pub(crate) skin: Option<&'a str>,
+    pub(crate) backface_culling: bool,
+    pub(crate) animation: Option<AnimationOptions>,
 }
 
 #[derive(Clone)]
@@ -38,6 +47,9 @@ pub struct LoadSpineFromFile<'a> {
     pub scale: f32,
     pub skin: Option<String>,
     pub backface_culling: bool,
+    /// The mix duration used when no mix is set for a pair of animations.
+    pub default_mix: f32,
+    pub mixes: Vec<AnimationMix>,
 }
 #[derive(Clone)]
 pub struct LoadSpineFromBytes<'a> {
@@ -49,111 +61,173 @@ pub struct LoadSpineFromBytes<'a> {
     pub scale: f32,
     pub skin: Option<String>,
     pub backface_culling: bool,
-    pub preloaded_texture: Option<Texture2D>,
+    /// The mix duration used when no mix is set for a pair of animations.
+    pub default_mix: f32,
+    pub mixes: Vec<AnimationMix>,
+    /// Textures for the pages of the atlas, keyed by the page name as written in the atlas.
+    ///
+    /// If this is empty the pages get loaded by the runtime instead.
+    /// Otherwise every page needs a texture.
+    pub preloaded_textures: HashMap<String, Texture2D>,
 }
 
 #[derive(Clone)]
 pub struct Spine {
     pub(crate) controller: Rc<RefCell<SkeletonController>>,
-    pub(crate) world: Mat4,
+    /// Kept to look up the textures of the atlas pages.
+    pub(crate) atlas: Arc<Atlas>,
+    /// The runtime the textures of the atlas pages are cached in.
+    pub(crate) runtime: SpineRuntime,
+    pub(crate) position: Vec2,
+    /// Rotation in radians
+    pub(crate) rotation: f32,
+    pub(crate) scale: Vec2,
     pub(crate) cull_face: CullFace,
+    pub(crate) bone_overrides: HashMap<String, BoneOverride>,
+    /// Targets of IK constraints in skeleton space, by constraint name.
+    pub(crate) ik_targets: HashMap<String, Vec2>,
+    pub(crate) disabled_constraints: HashMap<String, DisabledConstraint>,
+    pub(crate) tint_tween: Option<TintTween>,
 }
 
 impl Spine {
-    fn new(info: SpineCreationOptions, _token: crate::Token) -> Result<Self, SpineError> {
-        let skeleton_data = Arc::new(info.skeleton_data);
-        let premultiplied_alpha = info.atlas.pages().any(|v| v.pma());
-        let animation_state_data = Arc::new(AnimationStateData::new(skeleton_data.clone()));
-        let mut controller = SkeletonController::new(skeleton_data, animation_state_data)
-            .with_settings(SkeletonControllerSettings {
-                premultiplied_alpha,
-                cull_direction: CullDirection::CounterClockwise,
-                color_space: ColorSpace::SRGB,
-            });
+    pub(crate) fn new(
+        asset: &SpineAsset,
+        info: SpineCreationOptions,
+    ) -> Result<Self, SpineNodeError> {
+        let mut controller = SkeletonController::new(
+            asset.skeleton_data.clone(),
+            asset.animation_state_data.clone(),
+        )
+        .with_settings(SkeletonControllerSettings {
+            premultiplied_alpha: asset.premultiplied_alpha,
+            cull_direction: CullDirection::CounterClockwise,
+            color_space: ColorSpace::SRGB,
+        });
         if let Some(animation) = info.animation {
-            controller.animation_state.set_animation_by_name(
-                0,
-                &animation.animation_name,
-                animation.looping,
-            )?;
+            controller
+                .animation_state
+                .set_animation_by_name(0, &animation.animation_name, animation.looping)
+                .map_err(|e| SpineNodeError::new_animation(e, &animation.animation_name))?;
         }
         if let Some(skin) = info.skin {
-            controller.skeleton.set_skin_by_name(&skin)?;
+            controller
+                .skeleton
+                .set_skin_by_name(skin)
+                .map_err(|e| SpineNodeError::new_skin(e, skin))?;
         }
-        controller.settings.premultiplied_alpha = premultiplied_alpha;
         Ok(Self {
             controller: Rc::new(RefCell::new(controller)),
-            world: Mat4::from_translation(info.position.extend(0.))
-                * Mat4::from_scale(Vec2::splat(info.scale).extend(1.)),
+            atlas: asset.atlas.clone(),
+            runtime: asset.runtime.clone(),
+            position: info.position,
+            rotation: 0.,
+            scale: Vec2::splat(info.scale),
             cull_face: match info.backface_culling {
                 false => CullFace::Nothing,
                 true => CullFace::Back,
             },
+            bone_overrides: HashMap::new(),
+            ik_targets: HashMap::new(),
+            disabled_constraints: HashMap::new(),
+            tint_tween: None,
         })
     }
-    pub fn load_from_bytes(info: LoadSpineFromBytes, token: Token) -> Result<Self, SpineError> {
-        if let Some(preloaded_texture) = info.preloaded_texture {
-            let res: Vec<_> = info
-                .atlas
-                .iter()
-                .copied()
-                .take_while(|v| *v != b'\n')
-                .collect();
-            let name = String::from_utf8_lossy(&res);
-            let mut full_path = PathBuf::new();
-            full_path.push(info.dir_path.as_ref());
-            full_path.push(name.as_ref());
-            println!("{:?}", full_path);
-            add_to_cache(full_path, preloaded_texture);
+    pub(crate) fn world(&self) -> Mat4 {
+        Mat4::from_translation(self.position.extend(0.))
+            * Mat4::from_rotation_z(self.rotation)
+            * Mat4::from_scale(self.scale.extend(1.))
+    }
+    /// The cull face to render with.
+    ///
+    /// Flipping the skeleton on exactly one axis reverses the winding order of its triangles,
+    /// so the culled side has to be swapped to keep the skeleton visible.
+    pub(crate) fn effective_cull_face(&self) -> CullFace {
+        let mirrored = (self.scale.x < 0.) != (self.scale.y < 0.);
+        match (self.cull_face, mirrored) {
+            (CullFace::Back, true) => CullFace::Front,
+            (CullFace::Front, true) => CullFace::Back,
+            (cull_face, _) => cull_face,
         }
-        let atlas = Arc::new(Atlas::new(info.atlas.as_ref(), info.dir_path.as_ref())?);
-        let skeleton_data = match info.skeleton_data {
-            SpineSkeletonBytes::Binary(bytes) => {
-                let skeleton_binary = SkeletonBinary::new(atlas.clone());
-                skeleton_binary.read_skeleton_data(bytes.as_ref())?
-            }
-            SpineSkeletonBytes::Json(bytes) => {
-                let skeleton_json = SkeletonJson::new(atlas.clone());
-                skeleton_json.read_skeleton_data(bytes.as_ref())?
-            }
-        };
-        Self::new(
-            SpineCreationOptions {
-                position: info.position,
-                scale: info.scale,
-                skin: info.skin,
-                backface_culling: info.backface_culling,
-                animation: info.animation,
-                skeleton_data,
-                atlas,
-            },
-            token,
-        )
     }
-    pub fn load(info: LoadSpineFromFile, token: crate::Token) -> Result<Self, SpineError> {
-        let atlas = Arc::new(Atlas::new_from_file(info.atlas_path)?);
-
-        let skeleton_data = match info.skeleton_path {
-            SpineSkeletonPath::Binary(path) => {
-                let skeleton_binary = SkeletonBinary::new(atlas.clone());
-                skeleton_binary.read_skeleton_data_file(path.as_ref())?
-            }
-            SpineSkeletonPath::Json(path) => {
-                let skeleton_json = SkeletonJson::new(atlas.clone());
-                skeleton_json.read_skeleton_data_file(path.as_ref())?
+    pub(crate) fn set_mix_by_name(
+        &self,
+        from: &str,
+        to: &str,
+        duration: f32,
+    ) -> Result<(), SpineNodeError> {
+        let mut controller = self.controller.borrow_mut();
+        for name in [from, to] {
+            if controller.skeleton.data().find_animation(name).is_none() {
+                return Err(SpineNodeError::UnknownAnimation {
+                    name: name.to_string(),
+                });
             }
-        };
-        Self::new(
-            SpineCreationOptions {
-                position: info.position,
-                scale: info.scale,
-                skin: info.skin,
-                backface_culling: info.backface_culling,
-                animation: info.animation,
-                skeleton_data,
-                atlas,
-            },
-            token,
-        )
+        }
+        controller
+            .animation_state
+            .data_mut()
+            .set_mix_by_name(from, to, duration);
+        Ok(())
+    }
+    fn with_mixes(self, default_mix: f32, mixes: &[AnimationMix]) -> Result<Self, SpineNodeError> {
+        self.controller
+            .borrow_mut()
+            .animation_state
+            .data_mut()
+            .set_default_mix(default_mix);
+        for mix in mixes {
+            self.set_mix_by_name(&mix.from, &mix.to, mix.duration)?;
+        }
+        Ok(self)
+    }
+    pub fn load_from_bytes(
+        info: LoadSpineFromBytes,
+        runtime: SpineRuntime,
+    ) -> Result<Self, SpineNodeError> {
+        SpineAsset::load_from_bytes(
+            info.atlas.as_ref(),
+            info.dir_path.as_ref(),
+            info.skeleton_data,
+            &info.preloaded_textures,
+            &runtime,
+        )?
+        .instantiate(
+            info.position,
+            info.scale,
+            info.skin.as_deref(),
+            info.animation,
+            info.backface_culling,
+        )?
+        .with_mixes(info.default_mix, &info.mixes)
+    }
+    pub fn load(info: LoadSpineFromFile, runtime: SpineRuntime) -> Result<Self, SpineNodeError> {
+        SpineAsset::load(&info.atlas_path, info.skeleton_path, &runtime)?
+            .instantiate(
+                info.position,
+                info.scale,
+                info.skin.as_deref(),
+                info.animation,
+                info.backface_culling,
+            )?
+            .with_mixes(info.default_mix, &info.mixes)
+    }
+    /// Loads the atlas and the skeleton through macroquad's file api, and the texture of every atlas page through `runtime`.
+    ///
+    /// See [SpineAsset::load_async] for details.
+    pub async fn load_async(
+        info: LoadSpineFromFile<'_>,
+        runtime: SpineRuntime,
+    ) -> Result<Self, SpineNodeError> {
+        SpineAsset::load_async(&info.atlas_path, info.skeleton_path, &runtime)
+            .await?
+            .instantiate(
+                info.position,
+                info.scale,
+                info.skin.as_deref(),
+                info.animation,
+                info.backface_culling,
+            )?
+            .with_mixes(info.default_mix, &info.mixes)
     }
 }
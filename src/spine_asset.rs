@@ -0,0 +1,284 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    future::poll_fn,
+    path::{Path, PathBuf},
+    sync::Arc,
+    task::Poll,
+};
+
+use macroquad::{file::load_file, prelude::Vec2, texture::Texture2D};
+use rusty_spine::{AnimationStateData, Atlas, SkeletonBinary, SkeletonData, SkeletonJson};
+
+use crate::{
+    setup_rusty_spine::{texture_errors, PageTexture},
+    spine::SpineCreationOptions,
+    AnimationOptions, FallbackTexture, SkeletonInfo, Spine, SpineNodeError, SpineRuntime,
+    SpineSkeletonBytes, SpineSkeletonPath, SpineState,
+};
+
+/// A loaded atlas and skeleton that can be shared by any amount of [Spine] instances.
+///
+/// Loading and parsing only happens once, after which [SpineAsset::instantiate] is cheap.
+#[derive(Clone)]
+pub struct SpineAsset {
+    pub(crate) atlas: Arc<Atlas>,
+    pub(crate) skeleton_data: Arc<SkeletonData>,
+    pub(crate) animation_state_data: Arc<AnimationStateData>,
+    pub(crate) premultiplied_alpha: bool,
+    /// The runtime the textures of the atlas pages are cached in.
+    pub(crate) runtime: SpineRuntime,
+}
+
+/// Reads the names of every page in an atlas.
+///
+/// A page starts at the first line of the file and after every empty line.
+/// Older atlas files start with an empty line, which is handled the same way.
+fn atlas_page_names(atlas: &[u8]) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut expect_page = true;
+    for line in String::from_utf8_lossy(atlas).lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            expect_page = true;
+        } else if expect_page {
+            names.push(line.to_string());
+            expect_page = false;
+        }
+    }
+    names
+}
+
+async fn load_file_bytes(path: &str) -> Result<Vec<u8>, SpineNodeError> {
+    load_file(path).await.map_err(|e| SpineNodeError::Io {
+        path: path.to_string(),
+        reason: e.to_string(),
+    })
+}
+
+impl SpineAsset {
+    fn new(atlas: Arc<Atlas>, skeleton_data: SkeletonData, runtime: &SpineRuntime) -> Self {
+        let skeleton_data = Arc::new(skeleton_data);
+        let premultiplied_alpha = atlas.pages().any(|v| v.pma());
+        let animation_state_data = Arc::new(AnimationStateData::new(skeleton_data.clone()));
+        Self {
+            atlas,
+            skeleton_data,
+            animation_state_data,
+            premultiplied_alpha,
+            runtime: runtime.clone(),
+        }
+    }
+    /// Loads the atlas and skeleton directly from the filesystem.
+    ///
+    /// The textures of the atlas pages are cached in `runtime`.
+    pub fn load(
+        atlas_path: &str,
+        skeleton_path: SpineSkeletonPath,
+        runtime: &SpineRuntime,
+    ) -> Result<Self, SpineNodeError> {
+        let atlas = Arc::new(
+            runtime
+                .enter(|| Atlas::new_from_file(atlas_path))
+                .map_err(|e| SpineNodeError::new_atlas(e, Some(atlas_path)))?,
+        );
+
+        let skeleton_data = match skeleton_path {
+            SpineSkeletonPath::Binary(path) => {
+                let skeleton_binary = SkeletonBinary::new(atlas.clone());
+                skeleton_binary
+                    .read_skeleton_data_file(path.as_ref())
+                    .map_err(|e| SpineNodeError::new_skeleton(e, Some(&path)))?
+            }
+            SpineSkeletonPath::Json(path) => {
+                let skeleton_json = SkeletonJson::new(atlas.clone());
+                skeleton_json
+                    .read_skeleton_data_file(path.as_ref())
+                    .map_err(|e| SpineNodeError::new_skeleton(e, Some(&path)))?
+            }
+        };
+        Ok(Self::new(atlas, skeleton_data, runtime))
+    }
+    /// Loads the atlas and skeleton from memory.
+    ///
+    /// See [crate::LoadSpineFromBytes::preloaded_textures] for how `preloaded_textures` is used.
+    pub fn load_from_bytes(
+        atlas: &[u8],
+        dir_path: &str,
+        skeleton_data: SpineSkeletonBytes,
+        preloaded_textures: &HashMap<String, Texture2D>,
+        runtime: &SpineRuntime,
+    ) -> Result<Self, SpineNodeError> {
+        if !preloaded_textures.is_empty() {
+            let mut textures = Vec::new();
+            for page_name in atlas_page_names(atlas) {
+                let mut full_path = PathBuf::new();
+                full_path.push(dir_path);
+                full_path.push(&page_name);
+                let Some(texture) = preloaded_textures.get(&page_name) else {
+                    return Err(SpineNodeError::MissingTexture {
+                        page: page_name,
+                        path: full_path.to_string_lossy().into_owned(),
+                    });
+                };
+                textures.push((full_path, texture.clone()));
+            }
+            for (full_path, texture) in textures {
+                runtime.add_to_cache(full_path, texture);
+            }
+        }
+        let atlas = Arc::new(
+            runtime
+                .enter(|| Atlas::new(atlas, dir_path))
+                .map_err(|e| SpineNodeError::new_atlas(e, None))?,
+        );
+        let skeleton_data = match skeleton_data {
+            SpineSkeletonBytes::Binary(bytes) => {
+                let skeleton_binary = SkeletonBinary::new(atlas.clone());
+                skeleton_binary
+                    .read_skeleton_data(bytes.as_ref())
+                    .map_err(|e| SpineNodeError::new_skeleton(e, None))?
+            }
+            SpineSkeletonBytes::Json(bytes) => {
+                let skeleton_json = SkeletonJson::new(atlas.clone());
+                skeleton_json
+                    .read_skeleton_data(bytes.as_ref())
+                    .map_err(|e| SpineNodeError::new_skeleton(e, None))?
+            }
+        };
+        Ok(Self::new(atlas, skeleton_data, runtime))
+    }
+    /// Loads the atlas and the skeleton through macroquad's file api,
+    /// then waits for the [crate::TextureProvider] of `runtime` to load the texture of every atlas page.
+    ///
+    /// Unlike [SpineAsset::load] this does not need direct access to the filesystem,
+    /// so it also works on platforms where [Atlas::new_from_file] can't read the files.
+    /// All atlas pages are loaded concurrently.
+    ///
+    /// Pages that fail to load get the [FallbackTexture] of `runtime`, just like with the other loaders.
+    /// Only with [FallbackTexture::Fail] is this an error, [SpineNodeError::MissingTexture] for the first such page.
+    pub async fn load_async(
+        atlas_path: &str,
+        skeleton_path: SpineSkeletonPath<'_>,
+        runtime: &SpineRuntime,
+    ) -> Result<Self, SpineNodeError> {
+        let dir_path = Path::new(atlas_path)
+            .parent()
+            .and_then(Path::to_str)
+            .unwrap_or_default()
+            .to_string();
+        let skeleton_data = async {
+            Ok::<_, SpineNodeError>(match &skeleton_path {
+                SpineSkeletonPath::Binary(path) => {
+                    SpineSkeletonBytes::Binary(Cow::Owned(load_file_bytes(path).await?))
+                }
+                SpineSkeletonPath::Json(path) => {
+                    SpineSkeletonBytes::Json(Cow::Owned(load_file_bytes(path).await?))
+                }
+            })
+        };
+        let (atlas, skeleton_data) = futures::join!(load_file_bytes(atlas_path), skeleton_data);
+        // Creating the atlas starts loading the textures of its pages through the runtime
+        let asset =
+            Self::load_from_bytes(&atlas?, &dir_path, skeleton_data?, &HashMap::new(), runtime)?;
+        asset.wait_for_textures().await;
+        if runtime.fallback_texture() == FallbackTexture::Fail {
+            if let Some(error) = texture_errors(&asset.atlas).into_iter().next() {
+                return Err(SpineNodeError::MissingTexture {
+                    page: error.page,
+                    path: error.path.to_string_lossy().into_owned(),
+                });
+            }
+        }
+        Ok(asset)
+    }
+    /// Waits until the texture of every atlas page is done loading, including the ones that failed to.
+    async fn wait_for_textures(&self) {
+        poll_fn(|cx| {
+            let mut loaded = true;
+            for page in self.atlas.pages() {
+                let mut renderer_object = page.renderer_object();
+                if let Some(page_texture) = unsafe { renderer_object.get::<PageTexture>() } {
+                    page_texture.state.process();
+                    loaded &= page_texture.state.is_loaded();
+                }
+            }
+            if loaded {
+                Poll::Ready(())
+            } else {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+        .await
+    }
+    pub fn atlas(&self) -> &Arc<Atlas> {
+        &self.atlas
+    }
+    pub fn skeleton_data(&self) -> &Arc<SkeletonData> {
+        &self.skeleton_data
+    }
+    pub fn animation_state_data(&self) -> &Arc<AnimationStateData> {
+        &self.animation_state_data
+    }
+    /// Takes a snapshot of everything the skeleton contains.
+    pub fn skeleton_info(&self) -> SkeletonInfo {
+        SkeletonInfo::from(self.skeleton_data.as_ref())
+    }
+    /// Creates a new [Spine] that shares its atlas and skeleton data with this asset.
+    pub fn instantiate(
+        &self,
+        position: Vec2,
+        scale: f32,
+        skin: Option<&str>,
+        animation: Option<AnimationOptions>,
+        backface_culling: bool,
+    ) -> Result<Spine, SpineNodeError> {
+        Spine::new(
+            self,
+            SpineCreationOptions {
+                position,
+                scale,
+                skin,
+                backface_culling,
+                animation,
+            },
+        )
+    }
+    /// Same as [SpineAsset::instantiate] but also turns it into a [SpineState].
+    pub fn instantiate_state(
+        &self,
+        position: Vec2,
+        scale: f32,
+        skin: Option<&str>,
+        animation: Option<AnimationOptions>,
+        backface_culling: bool,
+    ) -> Result<SpineState, SpineNodeError> {
+        let spine = self.instantiate(position, scale, skin, animation, backface_culling)?;
+        SpineState::new(spine, self.runtime.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::atlas_page_names;
+
+    #[test]
+    fn page_names_of_bundled_atlas() {
+        let atlas = include_bytes!("../examples/asset_exports/skeleton.atlas");
+        assert_eq!(atlas_page_names(atlas), ["skeleton.png"]);
+    }
+
+    #[test]
+    fn page_names_of_multiple_pages() {
+        let atlas = b"first.png\nsize:2,2\nregion\n  bounds:0,0,1,1\n\nsecond.png\nsize:2,2\n";
+        assert_eq!(atlas_page_names(atlas), ["first.png", "second.png"]);
+    }
+
+    #[test]
+    fn page_names_of_old_format() {
+        let atlas =
+            b"\nfirst.png\nformat: RGBA8888\nregion\n  xy: 0, 0\n\nsecond.png\nformat: RGBA8888\n";
+        assert_eq!(atlas_page_names(atlas), ["first.png", "second.png"]);
+    }
+}
@@ -1,36 +1,14 @@
-use std::path::PathBuf;
-
-use macroquad::{
-    miniquad::{Bindings, BlendState, BufferSource, BufferType, BufferUsage, UniformsSource},
-    prelude::{warn, Vec2},
-    texture::Texture2D,
-    window::get_internal_gl,
-};
-use monad_quad::{
-    components::{asyncs::AsyncState, Context},
-    Component,
-};
-use rusty_spine::Color;
+use macroquad::{miniquad::PassAction, prelude::warn, texture::Texture2D, window::get_internal_gl};
+use monad_quad::{components::Context, Component};
 
 use crate::{
     animation_event::AnimationEvent,
-    blend_states::{BlendStates, GetBlendStates},
-    create_pipeline,
-    setup_rusty_spine::get_cache_item,
+    bounds::{is_visible, renderables_aabb},
+    render_buffers::combined_renderables,
+    setup_rusty_spine::PageTexture,
     shader, SpineState,
 };
 
-const MAX_MESH_VERTICES: usize = 10000;
-const MAX_MESH_INDICES: usize = 5000;
-
-#[repr(C)]
-struct Vertex {
-    position: Vec2,
-    uv: Vec2,
-    color: Color,
-    dark_color: Color,
-}
-
 pub struct AnimationStateWithData<T> {
     pub animation_state: SpineState,
     pub extra_data: T,
@@ -58,14 +36,20 @@ impl<T, Func: Fn(AnimationEvent, &mut AnimationStateWithData<T>)>
         state: &'c mut AnimationStateWithData<T>,
     ) -> &'c mut AnimationStateWithData<T> {
         state.animation_state.process_loading();
+        state.animation_state.view(context);
         let delta = context.get_delta();
-        state
-            .animation_state
-            .spine
-            .borrow_mut()
-            .controller
-            .borrow_mut()
-            .update(delta);
+        state.animation_state.spine.borrow_mut().update_tint(delta);
+        {
+            let spine = state.animation_state.spine.borrow();
+            let mut controller = spine.controller.borrow_mut();
+            let controller = &mut *controller;
+            controller.animation_state.update(delta);
+            controller.animation_state.apply(&mut controller.skeleton);
+            spine.apply_bone_overrides(&mut controller.skeleton);
+            spine.apply_disabled_constraints(&mut controller.skeleton);
+            spine.apply_ik_targets(&mut controller.skeleton);
+            controller.skeleton.update_world_transform();
+        }
         if state.animation_state.events.borrow().is_empty() {
             return state;
         }
@@ -79,99 +63,37 @@ impl<T, Func: Fn(AnimationEvent, &mut AnimationStateWithData<T>)>
     }
 
     fn render(&self, context: &Context, props: &AnimationStateWithData<T>) {
-        let renderables = props
-            .animation_state
-            .spine
-            .borrow()
-            .controller
-            .borrow_mut()
-            .combined_renderables();
-        let gl_context = unsafe { get_internal_gl().quad_context };
-        // Create bindings that can be re-used for rendering Spine meshes
-        while renderables.len() > props.animation_state.bindings.borrow().len() {
-            let buffer_source = BufferSource::empty::<Vertex>(MAX_MESH_VERTICES);
-            let vertex_buffer =
-                gl_context.new_buffer(BufferType::VertexBuffer, BufferUsage::Stream, buffer_source);
-            let buffer_source = BufferSource::empty::<u16>(MAX_MESH_INDICES);
-            let index_buffer =
-                gl_context.new_buffer(BufferType::IndexBuffer, BufferUsage::Stream, buffer_source);
-
-            props.animation_state.bindings.borrow_mut().push(Bindings {
-                vertex_buffers: vec![vertex_buffer],
-                index_buffer,
-                images: vec![],
-            });
-        }
-
-        // Delete textures that are no longer used. The delete call needs to happen here, before
-        // rendering, or it may not actually delete the texture.
-        props
-            .animation_state
-            .texture_delete_queue
-            .delete_previous(gl_context);
-
-        // Apply backface culling only if this skeleton needs it
-        //gl_context.set_cull_face(props.spine.cull_face);
+        let renderables = {
+            let spine = props.animation_state.spine.borrow();
+            let mut controller = spine.controller.borrow_mut();
+            combined_renderables(&mut controller)
+        };
+        // Delete textures that are no longer used. This needs to happen while macroquad is
+        // running, which is why it happens here instead of when the atlas gets disposed.
+        props.animation_state.runtime.delete_unused_textures();
 
         let view = props.animation_state.view(context);
-        let ctx = gl_context;
-        let mut last_blend_state: Option<(BlendState, BlendState)> = None;
-        let mut pipeline = props.animation_state.pipeline;
-        for (renderable, bindings) in renderables
-            .into_iter()
-            .zip(props.animation_state.bindings.borrow_mut().iter_mut())
-        {
-            // Set blend state based on this renderable's blend mode
-            let BlendStates {
-                alpha_blend,
-                color_blend,
-            } = renderable.blend_mode.get_blend_states(
-                props
-                    .animation_state
-                    .spine
-                    .borrow()
-                    .controller
-                    .borrow_mut()
-                    .settings
-                    .premultiplied_alpha,
-            );
-            let change_pipeline = match last_blend_state {
-                None => true,
-                Some(x) if x.0 != alpha_blend || x.1 != color_blend => true,
-                Some(_) => false,
-            };
-            if change_pipeline {
-                last_blend_state = Some((alpha_blend, color_blend));
-                pipeline =
-                    create_pipeline(ctx, &props.animation_state.spine.borrow(), last_blend_state)
-                        .unwrap();
-            }
-            ctx.apply_pipeline(&pipeline);
-
-            // Create the vertex and index buffers for miniquad
-            let mut vertices = vec![];
-            for vertex_index in 0..renderable.vertices.len() {
-                vertices.push(Vertex {
-                    position: Vec2 {
-                        x: renderable.vertices[vertex_index][0],
-                        y: renderable.vertices[vertex_index][1],
-                    },
-                    uv: Vec2 {
-                        x: renderable.uvs[vertex_index][0],
-                        y: renderable.uvs[vertex_index][1],
-                    },
-                    color: Color::from(renderable.colors[vertex_index]),
-                    dark_color: Color::from(renderable.dark_colors[vertex_index]),
-                });
-            }
-            ctx.buffer_update(bindings.vertex_buffers[0], BufferSource::slice(&vertices));
-            //bindings.vertex_buffers[0].update(ctx, &vertices);
-            ctx.buffer_update(
-                bindings.index_buffer,
-                BufferSource::slice(&renderable.indices),
-            );
-            //bindings.index_buffer.update(ctx, &renderable.indices);
-
+        // Skip uploading and drawing entirely if the skeleton is outside of the view
+        let world = props.animation_state.world();
+        match renderables_aabb(&renderables) {
+            Some(aabb) if is_visible(aabb, view * world) => {}
+            _ => return,
+        }
+        // Draw everything macroquad has batched so far, so the skeleton ends up on top of it
+        // instead of in whatever order macroquad decides to draw its batches.
+        let mut gl = unsafe { get_internal_gl() };
+        gl.flush();
+        let ctx = gl.quad_context;
+        ctx.begin_pass(props.animation_state.render_pass(), PassAction::Nothing);
+        let (premultiplied_alpha, cull_face) = {
+            let spine = props.animation_state.spine.borrow();
+            let premultiplied_alpha = spine.controller.borrow().settings.premultiplied_alpha;
+            (premultiplied_alpha, spine.effective_cull_face())
+        };
+        let mut pipelines = props.animation_state.pipelines.borrow_mut();
+        let mut render_buffers = props.animation_state.render_buffers.borrow_mut();
+        let uniforms = shader::Uniforms { world, view };
+        for renderable in renderables {
             // If there is no attachment (and therefore no texture), skip rendering this renderable
             let Some(attachment_renderer_object) = renderable.attachment_renderer_object else { continue };
             if attachment_renderer_object.is_null() {
@@ -180,30 +102,32 @@ impl<T, Func: Fn(AnimationEvent, &mut AnimationStateWithData<T>)>
                 warn!("Skipping for now.");
                 continue;
             }
-            // Load textures if they haven't been loaded already
+            // Pages that are still loading are skipped until they are done
             let spine_texture =
-                unsafe { &mut *(attachment_renderer_object as *mut AsyncState<PathBuf>) };
-            let texture = match spine_texture.get_value() {
-                None => {
-                    println!("is not loaded");
-                    continue;
+                unsafe { PageTexture::from_renderer_object(attachment_renderer_object) };
+            let Some(texture) = spine_texture.state.get_value().cloned() else {
+                continue;
+            };
+            let Some(texture) = spine_texture
+                .runtime
+                .get_cache_item(&texture, |v| v.map(Texture2D::raw_miniquad_id))
+            else {
+                // Pages that failed to load without a fallback are reported by texture_errors
+                if spine_texture.runtime.texture_error(&texture).is_none() {
+                    warn!("Texture {texture:?} is not in the cache. Skipping for now.");
                 }
-                Some(x) => x.to_owned(),
+                continue;
             };
-            let texture = get_cache_item(&texture, |v| v.map(Texture2D::raw_miniquad_id).unwrap());
-            bindings.images = vec![texture];
-            // Draw this renderable
-
-            ctx.apply_bindings(bindings);
 
-            ctx.apply_uniforms(UniformsSource::table(&shader::Uniforms {
-                world: props.animation_state.spine.borrow().world,
-                view,
-            }));
-            ctx.draw(0, renderable.indices.len() as i32, 1);
+            // Use the pipeline with the blend state of this renderable's blend mode
+            let pipeline =
+                pipelines.get(ctx, cull_face, renderable.blend_mode, premultiplied_alpha);
+            render_buffers.push(ctx, &uniforms, &renderable, pipeline, texture);
         }
+        // Draw whatever is still staged
+        render_buffers.flush(ctx, &uniforms);
 
-        // End frame
+        // End the pass started above, before macroquad starts drawing again
         ctx.end_render_pass();
     }
 }
@@ -0,0 +1,91 @@
+use macroquad::color::Color;
+
+use crate::{
+    slots::{from_spine_color, to_spine_color},
+    Spine, SpineState,
+};
+
+/// A linear transition of the skeleton color.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TintTween {
+    from: Color,
+    to: Color,
+    duration: f32,
+    elapsed: f32,
+}
+
+fn lerp_color(from: Color, to: Color, t: f32) -> Color {
+    Color::new(
+        from.r + (to.r - from.r) * t,
+        from.g + (to.g - from.g) * t,
+        from.b + (to.b - from.b) * t,
+        from.a + (to.a - from.a) * t,
+    )
+}
+
+impl Spine {
+    /// Advances the running tint tween, if any, and writes the result to the skeleton.
+    pub(crate) fn update_tint(&mut self, delta: f32) {
+        let Some(tween) = &mut self.tint_tween else {
+            return;
+        };
+        tween.elapsed += delta;
+        let t = if tween.duration > 0. {
+            (tween.elapsed / tween.duration).min(1.)
+        } else {
+            1.
+        };
+        let color = lerp_color(tween.from, tween.to, t);
+        if t >= 1. {
+            self.tint_tween = None;
+        }
+        *self.controller.borrow_mut().skeleton.color_mut() = to_spine_color(color);
+    }
+}
+
+impl SpineState {
+    /// The color every slot gets multiplied with. Defaults to white.
+    pub fn tint(&self) -> Color {
+        from_spine_color(self.spine.borrow().controller.borrow().skeleton.color())
+    }
+    /// Sets the color every slot gets multiplied with, including its alpha.
+    ///
+    /// This stops any running tween.
+    /// Premultiplied alpha is applied after the tint, so fading works for both kinds of atlases.
+    pub fn set_tint(&mut self, tint: Color) {
+        let mut spine = self.spine.borrow_mut();
+        spine.tint_tween = None;
+        *spine.controller.borrow_mut().skeleton.color_mut() = to_spine_color(tint);
+    }
+    pub fn alpha(&self) -> f32 {
+        self.tint().a
+    }
+    /// Sets the alpha of the whole skeleton while keeping the color of the tint.
+    ///
+    /// This stops any running tween.
+    pub fn set_alpha(&mut self, alpha: f32) {
+        let tint = self.tint();
+        self.set_tint(Color { a: alpha, ..tint });
+    }
+    /// Transitions the tint from its current value to `tint` over `duration` seconds.
+    ///
+    /// Replaces any running tween, starting from wherever that tween currently is.
+    pub fn tween_tint(&mut self, tint: Color, duration: f32) {
+        let from = self.tint();
+        self.spine.borrow_mut().tint_tween = Some(TintTween {
+            from,
+            to: tint,
+            duration,
+            elapsed: 0.,
+        });
+    }
+    /// Transitions the alpha of the whole skeleton to `alpha` over `duration` seconds.
+    pub fn fade_to(&mut self, alpha: f32, duration: f32) {
+        let tint = self.tint();
+        self.tween_tint(Color { a: alpha, ..tint }, duration);
+    }
+    /// Whether a tween started by [SpineState::tween_tint] or [SpineState::fade_to] is still running.
+    pub fn is_tweening_tint(&self) -> bool {
+        self.spine.borrow().tint_tween.is_some()
+    }
+}